@@ -1,5 +1,6 @@
-use er_rusted::ui::{Repl, Script, Tui, UI};
+use er_rusted::ui::{Config, Define, ErrorPolicy, Repl, ReportFormat, Script, Tui, Vars, Walk, UI};
 use eyre::{bail, WrapErr};
+use regex::Regex;
 use structopt::StructOpt;
 
 #[derive(StructOpt)]
@@ -15,21 +16,111 @@ struct Opt {
     #[structopt(short = "e", long = "expr", name = "expr", conflicts_with("file"))]
     expressions: Vec<String>,
 
+    #[structopt(short = "D", long = "define", name = "name=value")]
+    defines: Vec<Define>,
+
+    /// What to do when a command fails on a file: abort, skip or ignore
+    #[structopt(long = "on-error", name = "policy", default_value = "skip")]
+    on_error: ErrorPolicy,
+
+    /// Do not count a substitution that replaces nothing as a failure
+    #[structopt(short = "n", long = "no-match-ok")]
+    no_match_ok: bool,
+
+    /// Print a unified diff of what would change instead of writing files
+    #[structopt(long = "dry-run", alias = "diff")]
+    dry_run: bool,
+
+    /// Write no files unless the script succeeds on all of them
+    #[structopt(long = "atomic")]
+    atomic: bool,
+
+    /// Print a report on every file for other programs: json
+    #[structopt(long = "report", name = "format")]
+    report: Option<ReportFormat>,
+
+    /// Run on this many files at once
+    #[structopt(short = "j", long = "jobs", name = "N", default_value = "1")]
+    jobs: usize,
+
+    /// Run on the files under a directory, skipping ignored, hidden and binary files
+    #[structopt(short = "r", long = "recursive", name = "dir")]
+    recursive: Vec<String>,
+
+    /// Only take files matching this glob from directories
+    #[structopt(long = "include", name = "glob")]
+    include: Vec<String>,
+
+    /// Leave out files matching this glob from directories
+    #[structopt(long = "exclude", name = "exclude-glob")]
+    exclude: Vec<String>,
+
+    /// Only run on files with a line matching this regex
+    #[structopt(long = "contains", name = "regex")]
+    contains: Option<Regex>,
+
+    /// Record the commands typed in the session to a file as a script
+    #[structopt(long = "record", name = "journal")]
+    record: Option<String>,
+
+    /// Change a setting, after any config files are loaded
+    #[structopt(long = "set", name = "setting=value")]
+    settings: Vec<String>,
+
+    /// Skip loading ~/.config/er/config.er and the local .errc
+    #[structopt(long = "no-config")]
+    no_config: bool,
+
     #[structopt(short = "T", long = "disable-visual")]
     disable_visual: bool,
 }
 
 fn main() -> eyre::Result<()> {
     let opt = Opt::from_args();
+    let vars = Vars::new(opt.defines);
+    let mut config = if opt.no_config {
+        Config::from_env()
+    } else {
+        Config::load()?
+    };
+    for assignment in &opt.settings {
+        config.set(assignment)?;
+    }
+
+    let mut files = opt.files;
+    let walk = Walk::new(opt.include, opt.exclude);
+    for dir in &opt.recursive {
+        files.extend(walk.files(dir)?);
+    }
+
+    let scripted = opt.script.is_some() || !opt.expressions.is_empty();
+    if scripted && !opt.recursive.is_empty() && files.is_empty() {
+        eprintln!("no files found under {}", opt.recursive.join(" "));
+        return Ok(());
+    }
 
     if let Some(file) = opt.script {
-        let mut script = Script::from_file(&file, opt.inplace, opt.files)
-            .wrap_err("failed to build script from file")?;
+        let mut script = Script::from_file(&file, &vars, opt.inplace, files)
+            .wrap_err("failed to build script from file")?
+            .with_policy(opt.on_error, opt.no_match_ok)
+            .dry_run(opt.dry_run)
+            .jobs(opt.jobs)
+            .contains(opt.contains)
+            .atomic(opt.atomic)
+            .report(opt.report)
+            .config(config);
 
         script.run()
     } else if !opt.expressions.is_empty() {
-        let mut script = Script::from_expr(opt.expressions, opt.inplace, opt.files)
-            .wrap_err("failed to build script from expression")?;
+        let mut script = Script::from_expr(opt.expressions, &vars, opt.inplace, files)
+            .wrap_err("failed to build script from expression")?
+            .with_policy(opt.on_error, opt.no_match_ok)
+            .dry_run(opt.dry_run)
+            .jobs(opt.jobs)
+            .contains(opt.contains)
+            .atomic(opt.atomic)
+            .report(opt.report)
+            .config(config);
 
         script.run()
     } else {
@@ -38,11 +129,19 @@ fn main() -> eyre::Result<()> {
         }
 
         if !opt.disable_visual {
-            let mut tui = Tui::new(opt.files).wrap_err("failed to build tui")?;
+            let mut tui = Tui::new(files).wrap_err("failed to build tui")?;
+            tui.configure(&config)?;
+            if let Some(path) = &opt.record {
+                tui.record_to(path)?;
+            }
 
             tui.run()
         } else {
-            let mut repl = Repl::new(opt.files).wrap_err("failed to build ui")?;
+            let mut repl = Repl::new(files).wrap_err("failed to build ui")?;
+            repl.configure(&config)?;
+            if let Some(path) = &opt.record {
+                repl.record_to(path)?;
+            }
 
             repl.run()
         }
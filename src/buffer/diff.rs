@@ -0,0 +1,290 @@
+use super::Buffer;
+use std::fmt::Write;
+use std::ops::{Index, IndexMut};
+
+/// Lines of context kept around each change
+const CONTEXT: usize = 3;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Edit<'a> {
+    Keep(&'a str),
+    Delete(&'a str),
+    Insert(&'a str),
+}
+
+impl Buffer {
+    /// A unified diff turning this buffer into another, empty when they have the same lines
+    pub fn diff(&self, updated: &Buffer, name: &str) -> String {
+        let edits = edits(&self.lines, &updated.lines);
+        let mut out = String::new();
+
+        if edits.iter().all(|edit| matches!(edit, Edit::Keep(_))) {
+            return out;
+        }
+
+        writeln!(out, "--- {}", name).unwrap();
+        writeln!(out, "+++ {}", name).unwrap();
+
+        for hunk in hunks(&edits) {
+            write_hunk(&mut out, &edits, hunk);
+        }
+
+        out
+    }
+
+    /// How many lines differ in another buffer, where a run of deleted lines replaced by
+    /// inserted ones counts as the longer of the two
+    pub fn changed_lines(&self, updated: &Buffer) -> usize {
+        let (mut changed, mut deleted, mut inserted) = (0, 0, 0);
+
+        for edit in edits(&self.lines, &updated.lines) {
+            match edit {
+                Edit::Keep(_) => {
+                    changed += deleted.max(inserted);
+                    deleted = 0;
+                    inserted = 0;
+                }
+                Edit::Delete(_) => deleted += 1,
+                Edit::Insert(_) => inserted += 1,
+            }
+        }
+
+        changed + deleted.max(inserted)
+    }
+}
+
+/// The shortest edit script between two lists of lines (Myers' algorithm)
+///
+/// The linear space variant is used, splitting on the middle snake of each part, so a file
+/// where nearly every line changed doesn't need memory for every step of the search.
+fn edits<'a>(old: &'a [String], new: &'a [String]) -> Vec<Edit<'a>> {
+    let max = max_d(old.len(), new.len());
+    let mut forward = Diagonals::new(max);
+    let mut backward = Diagonals::new(max);
+    let mut edits = Vec::with_capacity(old.len().max(new.len()));
+
+    conquer(old, new, &mut forward, &mut backward, &mut edits);
+
+    // within a change, deleted lines come before the lines inserted in their place
+    let mut start = 0;
+    while start < edits.len() {
+        let end = edits[start..]
+            .iter()
+            .position(|edit| matches!(edit, Edit::Keep(_)))
+            .map_or(edits.len(), |pos| start + pos);
+
+        edits[start..end].sort_by_key(|edit| matches!(edit, Edit::Insert(_)));
+        start = end + 1;
+    }
+
+    edits
+}
+
+/// The furthest x reached on each diagonal k, indexed from -max to max
+struct Diagonals {
+    offset: isize,
+    v: Vec<usize>,
+}
+
+impl Diagonals {
+    fn new(max: usize) -> Diagonals {
+        Diagonals {
+            offset: max as isize,
+            v: vec![0; 2 * max + 1],
+        }
+    }
+}
+
+impl Index<isize> for Diagonals {
+    type Output = usize;
+
+    fn index(&self, k: isize) -> &usize {
+        &self.v[(k + self.offset) as usize]
+    }
+}
+
+impl IndexMut<isize> for Diagonals {
+    fn index_mut(&mut self, k: isize) -> &mut usize {
+        &mut self.v[(k + self.offset) as usize]
+    }
+}
+
+/// How many steps from each end are needed before the searches must meet
+fn max_d(n: usize, m: usize) -> usize {
+    (n + m).div_ceil(2) + 1
+}
+
+fn conquer<'a>(
+    old: &'a [String],
+    new: &'a [String],
+    forward: &mut Diagonals,
+    backward: &mut Diagonals,
+    edits: &mut Vec<Edit<'a>>,
+) {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    edits.extend(old[..prefix].iter().map(|l| Edit::Keep(l)));
+
+    let (a, b) = (
+        &old[prefix..old.len() - suffix],
+        &new[prefix..new.len() - suffix],
+    );
+
+    if a.is_empty() {
+        edits.extend(b.iter().map(|l| Edit::Insert(l)));
+    } else if b.is_empty() {
+        edits.extend(a.iter().map(|l| Edit::Delete(l)));
+    } else if let Some((x, y)) = middle_snake(a, b, forward, backward) {
+        conquer(&a[..x], &b[..y], forward, backward, edits);
+        conquer(&a[x..], &b[y..], forward, backward, edits);
+    } else {
+        edits.extend(a.iter().map(|l| Edit::Delete(l)));
+        edits.extend(b.iter().map(|l| Edit::Insert(l)));
+    }
+
+    edits.extend(old[old.len() - suffix..].iter().map(|l| Edit::Keep(l)));
+}
+
+/// Where the shortest edit script between two lists without a common first or last line
+/// can be split in two, found by searching from both ends at once until the paths overlap
+fn middle_snake(
+    a: &[String],
+    b: &[String],
+    forward: &mut Diagonals,
+    backward: &mut Diagonals,
+) -> Option<(usize, usize)> {
+    let (n, m) = (a.len(), b.len());
+    let delta = n as isize - m as isize;
+    let odd = delta & 1 == 1;
+
+    forward[1] = 0;
+    backward[1] = 0;
+
+    for d in 0..max_d(n, m) as isize {
+        for k in (-d..=d).rev().step_by(2) {
+            let mut x = if k == -d || (k != d && forward[k - 1] < forward[k + 1]) {
+                forward[k + 1]
+            } else {
+                forward[k - 1] + 1
+            };
+            let y = (x as isize - k) as usize;
+            let start = (x, y);
+
+            if x < n && y < m {
+                x += a[x..]
+                    .iter()
+                    .zip(&b[y..])
+                    .take_while(|(a, b)| a == b)
+                    .count();
+            }
+
+            forward[k] = x;
+
+            if odd && (k - delta).abs() < d && forward[k] + backward[delta - k] >= n {
+                return Some(start);
+            }
+        }
+
+        for k in (-d..=d).rev().step_by(2) {
+            let mut x = if k == -d || (k != d && backward[k - 1] < backward[k + 1]) {
+                backward[k + 1]
+            } else {
+                backward[k - 1] + 1
+            };
+            let mut y = (x as isize - k) as usize;
+
+            if x < n && y < m {
+                let common = a[..n - x]
+                    .iter()
+                    .rev()
+                    .zip(b[..m - y].iter().rev())
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                x += common;
+                y += common;
+            }
+
+            backward[k] = x;
+
+            if !odd && (k - delta).abs() <= d && backward[k] + forward[delta - k] >= n {
+                return Some((n - x, m - y));
+            }
+        }
+    }
+
+    None
+}
+
+/// Ranges of edits to show, each change padded with context and overlapping ones merged
+fn hunks(edits: &[Edit]) -> Vec<(usize, usize)> {
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+
+    for (i, edit) in edits.iter().enumerate() {
+        if let Edit::Keep(_) = edit {
+            continue;
+        }
+
+        let start = i.saturating_sub(CONTEXT);
+        let end = (i + 1 + CONTEXT).min(edits.len());
+
+        match hunks.last_mut() {
+            Some((_, last)) if *last >= start => *last = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    hunks
+}
+
+fn write_hunk(out: &mut String, edits: &[Edit], (start, end): (usize, usize)) {
+    let before = &edits[..start];
+    let old_start = before
+        .iter()
+        .filter(|e| !matches!(e, Edit::Insert(_)))
+        .count();
+    let new_start = before
+        .iter()
+        .filter(|e| !matches!(e, Edit::Delete(_)))
+        .count();
+
+    let hunk = &edits[start..end];
+    let old_len = hunk
+        .iter()
+        .filter(|e| !matches!(e, Edit::Insert(_)))
+        .count();
+    let new_len = hunk
+        .iter()
+        .filter(|e| !matches!(e, Edit::Delete(_)))
+        .count();
+
+    writeln!(
+        out,
+        "@@ -{} +{} @@",
+        span(old_start, old_len),
+        span(new_start, new_len)
+    )
+    .unwrap();
+
+    for edit in hunk {
+        match edit {
+            Edit::Keep(line) => writeln!(out, " {}", line).unwrap(),
+            Edit::Delete(line) => writeln!(out, "-{}", line).unwrap(),
+            Edit::Insert(line) => writeln!(out, "+{}", line).unwrap(),
+        }
+    }
+}
+
+/// A hunk's range of lines, which starts at the line before when it is empty
+fn span(before: usize, len: usize) -> String {
+    match len {
+        0 => format!("{},0", before),
+        1 => format!("{}", before + 1),
+        len => format!("{},{}", before + 1, len),
+    }
+}
@@ -2,11 +2,12 @@ use std::collections::HashMap;
 use std::io::{self, BufRead, BufReader, Read, Write};
 use std::vec::Drain;
 
+mod diff;
 #[cfg(test)]
 mod test;
 
 /// A buffer representing a file being edited
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Buffer {
     /// 1-based indexing in lines
     cur: usize,
@@ -133,9 +134,14 @@ impl Buffer {
         self.lines.len()
     }
 
+    /// all of the lines in order
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
+
     /// index lines 1-based
     pub fn line(&self, line: usize) -> Option<&str> {
-        if let Some(s) = self.lines.get(line - 1) {
+        if let Some(s) = self.lines.get(line.checked_sub(1)?) {
             Some(s.as_str())
         } else {
             None
@@ -260,6 +266,17 @@ impl Buffer {
         self.marks.get(&mark).cloned()
     }
 
+    /// Moves each mark to where its line went, dropping those whose line was removed
+    pub(crate) fn remap_marks(&mut self, moved: impl Fn(usize) -> Option<usize>) {
+        self.marks.retain(|_, pos| match moved(*pos) {
+            Some(line) => {
+                *pos = line;
+                true
+            }
+            None => false,
+        });
+    }
+
     /// Has the buffer been changed since calling the write method or has been created
     pub fn is_dirty(&self) -> bool {
         self.dirty
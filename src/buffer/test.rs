@@ -355,3 +355,81 @@ fn test_window() {
     assert_eq!(&buf.window(1, 4), &["hello", "world", "tschuess", "welt"]);
     assert_eq!(&buf.window(4, 4), &["welt"]);
 }
+
+mod diff {
+    use super::*;
+
+    fn buffer(content: &str) -> Buffer {
+        Buffer::read(content.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn same() {
+        assert_eq!("", buffer("a\nb\n").diff(&buffer("a\nb\n"), "f"));
+    }
+
+    #[test]
+    fn change() {
+        let old = buffer("1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n");
+        let new = buffer("1\n2\n3\n4\nfive\n6\n7\n8\n9\n10\n");
+
+        assert_eq!(
+            "--- f\n+++ f\n@@ -2,7 +2,7 @@\n 2\n 3\n 4\n-5\n+five\n 6\n 7\n 8\n",
+            old.diff(&new, "f")
+        );
+    }
+
+    #[test]
+    fn separate_hunks() {
+        let old = buffer("a\n1\n2\n3\n4\n5\n6\n7\nb\n");
+        let new = buffer("1\n2\n3\n4\n5\n6\n7\nb\nc\n");
+
+        assert_eq!(
+            "--- f\n+++ f\n@@ -1,4 +1,3 @@\n-a\n 1\n 2\n 3\n@@ -7,3 +6,4 @@\n 6\n 7\n b\n+c\n",
+            old.diff(&new, "f")
+        );
+    }
+
+    #[test]
+    fn changed_lines() {
+        let old = buffer("a\nb\nc\nd\n");
+
+        assert_eq!(0, old.changed_lines(&old.clone()));
+        assert_eq!(1, old.changed_lines(&buffer("a\nB\nc\nd\n")));
+        assert_eq!(3, old.changed_lines(&buffer("A\nb\nc\nd\ne\nf\n")));
+    }
+
+    #[test]
+    fn from_empty() {
+        assert_eq!(
+            "--- f\n+++ f\n@@ -0,0 +1,2 @@\n+x\n+y\n",
+            buffer("").diff(&buffer("x\ny\n"), "f")
+        );
+    }
+
+    #[test]
+    fn interleaved() {
+        let old = buffer("a\nb\nc\na\nb\nb\na\n");
+        let new = buffer("c\nb\na\nb\na\nc\n");
+
+        let diff = old.diff(&new, "f");
+        let removed = diff.lines().filter(|l| l.starts_with('-')).count() - 1;
+        let added = diff.lines().filter(|l| l.starts_with('+')).count() - 1;
+
+        assert_eq!((removed, added), (3, 2), "shortest edit");
+    }
+
+    #[test]
+    fn rewritten() {
+        let lines: Vec<String> = (1..=2000).map(|i| i.to_string()).collect();
+        let old = buffer(&format!("{}\n", lines.join("\n")));
+        let new = buffer(&format!("x{}\n", lines.join("\nx")));
+
+        let diff = old.diff(&new, "f");
+        let removed = diff.lines().filter(|l| l.starts_with('-')).count() - 1;
+        let added = diff.lines().filter(|l| l.starts_with('+')).count() - 1;
+
+        assert_eq!((removed, added), (2000, 2000));
+        assert_eq!(2000, old.changed_lines(&new));
+    }
+}
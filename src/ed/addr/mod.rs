@@ -4,8 +4,7 @@ use super::re::Re;
 mod test;
 
 /// A reference for a line in a buffer
-#[derive(Debug, Clone)]
-#[cfg_attr(test, derive(PartialEq))]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Point {
     /// The current point in the buffer (the cursor).
     Current,
@@ -27,8 +26,7 @@ pub enum Point {
 }
 
 /// A relative offset from a point
-#[derive(Debug, Clone)]
-#[cfg_attr(test, derive(PartialEq))]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Offset {
     /// Nil offset
     Nil(Point),
@@ -41,8 +39,7 @@ pub enum Offset {
 }
 
 /// An address describles a set of continuous lines
-#[derive(Debug, Clone)]
-#[cfg_attr(test, derive(PartialEq))]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Address {
     /// Only one line
     Line(Offset),
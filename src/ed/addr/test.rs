@@ -1,6 +1,7 @@
 use crate::ed::prelude::*;
 
 use crate::ed::parse::Parsable;
+use crate::ed::re::ReFlags;
 use nom::combinator::all_consuming;
 use std::str::FromStr;
 
@@ -25,6 +26,28 @@ fn parse_point() {
     assert_eq!(p, Point::Reb(re!("func *\\(?")));
 }
 
+#[test]
+fn parse_point_flags() {
+    let insensitive = ReFlags {
+        insensitive: true,
+        literal: false,
+    };
+
+    let literal = ReFlags {
+        insensitive: false,
+        literal: true,
+    };
+
+    let p = all_consuming(Point::parse)("/todo/I").unwrap().1;
+    assert_eq!(p, Point::Ref(Re::with_flags("todo", insensitive).unwrap()));
+
+    let p = all_consuming(Point::parse)("?a.b?L").unwrap().1;
+    assert_eq!(p, Point::Reb(Re::with_flags("a.b", literal).unwrap()));
+
+    let p = all_consuming(Point::parse)("/a\\/b/L").unwrap().1;
+    assert_eq!(p, Point::Ref(Re::with_flags("a/b", literal).unwrap()));
+}
+
 #[test]
 fn parse_offset() {
     let p = all_consuming(Offset::parse)(".").unwrap().1;
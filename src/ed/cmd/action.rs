@@ -1,8 +1,9 @@
 use super::*;
+use crate::ed::re::ExpandCtx;
 use crate::ed::syspoint::{Cmd, SysPoint};
 use crate::interp::scratch::ScratchPad;
-use crate::interp::Interpreter;
-use regex::Captures;
+use crate::interp::{settings::DEFAULT_SCROLL, write_hook::WriteHook, Interpreter};
+use regex::{Captures, Regex};
 use std::cmp::{max, min};
 use std::fs::File;
 use std::io::ErrorKind;
@@ -21,6 +22,12 @@ pub enum MarkMod {
         end: usize,
         delta: i64,
     },
+    Remap {
+        start: usize,
+        end: usize,
+        to: Vec<Option<usize>>,
+        delta: i64,
+    },
 }
 
 impl Command {
@@ -30,10 +37,14 @@ impl Command {
     ) -> Result<(bool, MarkMod), InvocationError> {
         use Command::*;
 
+        if interp.env.sandboxed && self.has_side_effects() {
+            return Err(InvocationError::Sandboxed);
+        }
+
         match self {
             Print(addr) => {
                 let (start, end) = addr
-                    .resolve_range(&interp.buffer)
+                    .resolve_range(&interp.buffer, &interp.env)
                     .ok_or(InvocationError::AddressNonResolvable)?;
 
                 for line in start..=end {
@@ -47,9 +58,9 @@ impl Command {
 
             Scroll(offset, num) => {
                 let line = offset
-                    .resolve_line(&interp.buffer)
+                    .resolve_line(&interp.buffer, &interp.env)
                     .ok_or(InvocationError::AddressNonResolvable)?;
-                let num = num.or(interp.env.scroll).unwrap_or(22);
+                let num = num.or(interp.env.scroll).unwrap_or(DEFAULT_SCROLL);
                 let pad = digits(interp.buffer.len());
 
                 for pos in line..(line + num) {
@@ -65,7 +76,7 @@ impl Command {
 
             Delete(addr) => {
                 let (start, end) = addr
-                    .resolve_range(&interp.buffer)
+                    .resolve_range(&interp.buffer, &interp.env)
                     .ok_or(InvocationError::AddressNonResolvable)?;
                 interp.buffer.remove(start, end);
                 interp.buffer.set_cursor(start);
@@ -80,7 +91,7 @@ impl Command {
 
             Mark(offset, mark) => {
                 let line = offset
-                    .resolve_line(&interp.buffer)
+                    .resolve_line(&interp.buffer, &interp.env)
                     .ok_or(InvocationError::AddressNonResolvable)?;
                 interp.buffer.make_mark(*mark, line);
 
@@ -89,7 +100,7 @@ impl Command {
 
             Join(addr) => {
                 let (start, end) = addr
-                    .resolve_range(&interp.buffer)
+                    .resolve_range(&interp.buffer, &interp.env)
                     .ok_or(InvocationError::AddressNonResolvable)?;
 
                 let lines: Vec<String> = interp
@@ -117,12 +128,76 @@ impl Command {
                 ))
             }
 
+            JoinWith(addr, sep) => {
+                let (start, end) = addr
+                    .resolve_range(&interp.buffer, &interp.env)
+                    .ok_or(InvocationError::AddressNonResolvable)?;
+
+                let lines = address_lines(&interp.buffer, start, end)?;
+
+                interp.buffer.change(start, end, vec![lines.join(sep)]);
+                interp.buffer.set_cursor(start);
+
+                // only the first line is left, the rest are joined onto it
+                let mut to = vec![None; lines.len()];
+                to[0] = Some(start);
+
+                let markmod = MarkMod::Remap {
+                    start,
+                    end,
+                    to,
+                    delta: 0 - (end - start) as i64,
+                };
+                interp.buffer.remap_marks(|line| markmod.moved(line));
+
+                Ok((true, markmod))
+            }
+
+            Split(addr, re, keep) => {
+                let (start, end) = addr
+                    .resolve_range(&interp.buffer, &interp.env)
+                    .ok_or(InvocationError::AddressNonResolvable)?;
+
+                let re = match (re, &interp.env.last_re) {
+                    (Some(re), _) | (None, Some(re)) => re.clone(),
+                    (None, None) => return Err(InvocationError::MissingPattern),
+                };
+
+                let lines = address_lines(&interp.buffer, start, end)?;
+
+                let mut to = Vec::with_capacity(lines.len());
+                let mut pieces = Vec::with_capacity(lines.len());
+
+                let matcher = re.matcher(interp.env.ignore_case);
+                for line in &lines {
+                    to.push(Some(start + pieces.len()));
+                    pieces.extend(split_line(line, matcher, *keep));
+                }
+
+                let delta = pieces.len() as i64 - lines.len() as i64;
+                let last = start + pieces.len() - 1;
+
+                interp.buffer.change(start, end, pieces);
+                interp.buffer.set_cursor(last);
+                interp.env.last_re = Some(re);
+
+                let markmod = MarkMod::Remap {
+                    start,
+                    end,
+                    to,
+                    delta,
+                };
+                interp.buffer.remap_marks(|line| markmod.moved(line));
+
+                Ok((true, markmod))
+            }
+
             Move(addr, offset) => {
                 let (start, end) = addr
-                    .resolve_range(&interp.buffer)
+                    .resolve_range(&interp.buffer, &interp.env)
                     .ok_or(InvocationError::AddressNonResolvable)?;
                 let target = offset
-                    .resolve_line(&interp.buffer)
+                    .resolve_line(&interp.buffer, &interp.env)
                     .ok_or(InvocationError::AddressNonResolvable)?;
 
                 if start <= target && target <= end {
@@ -160,10 +235,10 @@ impl Command {
 
             Transfer(addr, offset) => {
                 let (start, end) = addr
-                    .resolve_range(&interp.buffer)
+                    .resolve_range(&interp.buffer, &interp.env)
                     .ok_or(InvocationError::AddressNonResolvable)?;
                 let to = offset
-                    .resolve_line(&interp.buffer)
+                    .resolve_line(&interp.buffer, &interp.env)
                     .ok_or(InvocationError::AddressNonResolvable)?;
                 let lines = interp
                     .buffer
@@ -180,7 +255,7 @@ impl Command {
 
             Yank(addr) => {
                 let (start, end) = addr
-                    .resolve_range(&interp.buffer)
+                    .resolve_range(&interp.buffer, &interp.env)
                     .ok_or(InvocationError::AddressNonResolvable)?;
                 let lines = interp
                     .buffer
@@ -194,7 +269,7 @@ impl Command {
 
             Paste(offset) => {
                 let line = offset
-                    .resolve_line(&interp.buffer)
+                    .resolve_line(&interp.buffer, &interp.env)
                     .ok_or(InvocationError::AddressNonResolvable)?;
                 interp.buffer.append(line, interp.env.cut.clone());
 
@@ -208,7 +283,7 @@ impl Command {
 
             Write(addr, syncer, quit) => {
                 let (start, end) = addr
-                    .resolve_range(&interp.buffer)
+                    .resolve_range(&interp.buffer, &interp.env)
                     .ok_or(InvocationError::AddressNonResolvable)?;
                 let lines = interp
                     .buffer
@@ -226,7 +301,7 @@ impl Command {
 
             Read(offset, src) => {
                 let line = offset
-                    .resolve_line(&interp.buffer)
+                    .resolve_line(&interp.buffer, &interp.env)
                     .ok_or(InvocationError::AddressNonResolvable)?;
                 let lines = src
                     .source(&interp.buffer, &interp.env)
@@ -299,46 +374,111 @@ impl Command {
             }
 
             Subst(addr, re, pat, flags) => {
+                let (start, end, re, pat, flags) = subst_args(interp, addr, re, pat, flags)?;
+
+                let replaced = if flags.confirm {
+                    confirm_subst(interp, start, end, &re, &pat, flags)?
+                } else {
+                    let matcher = re.matcher(interp.env.ignore_case);
+                    run_subst(interp, start, end, matcher, &pat, &flags)?
+                };
+
+                if !replaced {
+                    return Err(InvocationError::DidNotReplace);
+                }
+
+                interp.env.substituted = true;
+                interp.env.last_re = Some(re);
+                interp.env.last_pat = Some(pat);
+                interp.env.last_flags = Some(flags);
+
+                Ok((true, MarkMod::Nil))
+            }
+
+            Translit(addr, pairs) => {
                 let (start, end) = addr
-                    .resolve_range(&interp.buffer)
+                    .resolve_range(&interp.buffer, &interp.env)
                     .ok_or(InvocationError::AddressNonResolvable)?;
 
-                let flags = flags.unwrap_or_else(|| {
-                    if re.is_none() && pat.is_none() {
-                        interp.env.last_flags.unwrap_or_default()
-                    } else {
-                        Default::default()
-                    }
-                });
+                if !run_translit(&mut interp.buffer, start, end, pairs) {
+                    return Err(InvocationError::DidNotReplace);
+                }
 
-                let re = match (re, &interp.env.last_re) {
-                    (Some(re), _) | (None, Some(re)) => re.clone(),
-                    (None, None) => return Err(InvocationError::MissingPattern),
+                Ok((true, MarkMod::Nil))
+            }
+
+            Increment(addr, amount, flags) => {
+                let (start, end) = addr
+                    .resolve_range(&interp.buffer, &interp.env)
+                    .ok_or(InvocationError::AddressNonResolvable)?;
+
+                if !increment::increment(&mut interp.buffer, start, end, *amount, *flags)? {
+                    return Err(InvocationError::DidNotReplace);
+                }
+
+                Ok((true, MarkMod::Nil))
+            }
+
+            Label(_) => Ok((true, MarkMod::Nil)),
+            Branch(..) => Err(InvocationError::BranchOutsideScript),
+
+            Source(src) => {
+                let key = match src {
+                    SysPoint::File(name) => std::fs::canonicalize(name)
+                        .map(|path| path.display().to_string())
+                        .unwrap_or_else(|_| name.to_string()),
+                    SysPoint::Command(Cmd::System(cmd)) => format!("!{}", cmd),
+                    _ => return Err(InvocationError::UnableToSource),
                 };
 
-                let pat = match (pat, &interp.env.last_pat) {
-                    (Some(Pat::Replay), None) | (None, None) => {
-                        return Err(InvocationError::MissingPattern)
-                    }
-                    (Some(Pat::Replay), Some(pat)) | (Some(pat), _) | (None, Some(pat)) => {
-                        pat.clone()
-                    }
+                if interp.env.sourcing.contains(&key) {
+                    return Err(InvocationError::RecursiveSource);
+                }
+
+                let content = src
+                    .source(&interp.buffer, &interp.env)
+                    .ok_or(InvocationError::UnableToSource)?
+                    .join("\n");
+
+                interp.env.sourcing.push(key);
+                let res = interp.run(&content);
+                interp.env.sourcing.pop();
+
+                Ok((res?, MarkMod::Nil))
+            }
+
+            Journal(dest) => {
+                let lines = interp.env.journal.lines().to_vec();
+
+                let written = match dest {
+                    SysPoint::File(name) => WriteHook::Id.sync(name, &mut interp.buffer, &lines),
+                    SysPoint::Command(cmd) => cmd.sync(&mut interp.buffer, &interp.env, &lines),
+                    SysPoint::Filename => return Err(InvocationError::InvalidTarget),
                 };
 
-                if !run_subst(
-                    &mut interp.buffer,
-                    start,
-                    end,
-                    &re,
-                    &pat,
-                    &flags,
-                    &mut interp.scratch,
-                ) {
-                    return Err(InvocationError::DidNotReplace);
+                if !written {
+                    return Err(InvocationError::FailedCommand);
                 }
 
-                interp.env.last_re = Some(re);
-                interp.env.last_pat = Some(pat);
+                Ok((true, MarkMod::Nil))
+            }
+
+            Set(setting, value) => {
+                setting.set(&mut interp.env, value)?;
+
+                Ok((true, MarkMod::Nil))
+            }
+
+            Settings(setting) => {
+                let settings = match setting {
+                    Some(setting) => std::slice::from_ref(setting),
+                    None => Setting::ALL,
+                };
+
+                for setting in settings {
+                    let out = format!("{}={}", setting.name(), setting.get(&interp.env));
+                    interp.scratch.print(&out);
+                }
 
                 Ok((true, MarkMod::Nil))
             }
@@ -347,7 +487,7 @@ impl Command {
 
             Global(addr, re, cmd_list) => {
                 let (start, end) = addr
-                    .resolve_range(&interp.buffer)
+                    .resolve_range(&interp.buffer, &interp.env)
                     .ok_or(InvocationError::AddressNonResolvable)?;
 
                 let re = match (re, &interp.env.last_re) {
@@ -355,17 +495,23 @@ impl Command {
                     (None, None) => Err(InvocationError::MissingPattern)?,
                 };
 
+                let matcher = re.matcher(interp.env.ignore_case);
                 let mut marked = Vec::new();
                 for pos in start..=end {
                     if let Some(line) = interp.buffer.line(pos) {
-                        if re.is_match(line) {
-                            marked.push(pos);
+                        if matcher.is_match(line) {
+                            marked.push(Some(pos));
                         }
                     }
                 }
 
                 for mark_idx in 0..marked.len() {
-                    interp.buffer.set_cursor(marked[mark_idx]);
+                    let pos = match marked[mark_idx] {
+                        Some(pos) => pos,
+                        None => continue,
+                    };
+
+                    interp.buffer.set_cursor(pos);
                     for cmd in cmd_list {
                         let (cont, markmod) = cmd.invoke(interp)?;
                         if !cont {
@@ -381,7 +527,7 @@ impl Command {
 
             Void(addr, re, cmd_list) => {
                 let (start, end) = addr
-                    .resolve_range(&interp.buffer)
+                    .resolve_range(&interp.buffer, &interp.env)
                     .ok_or(InvocationError::AddressNonResolvable)?;
 
                 let re = match (re, &interp.env.last_re) {
@@ -389,17 +535,23 @@ impl Command {
                     (None, None) => Err(InvocationError::MissingPattern)?,
                 };
 
+                let matcher = re.matcher(interp.env.ignore_case);
                 let mut marked = Vec::new();
                 for pos in start..=end {
                     if let Some(line) = interp.buffer.line(pos) {
-                        if !re.is_match(line) {
-                            marked.push(pos);
+                        if !matcher.is_match(line) {
+                            marked.push(Some(pos));
                         }
                     }
                 }
 
                 for mark_idx in 0..marked.len() {
-                    interp.buffer.set_cursor(marked[mark_idx]);
+                    let pos = match marked[mark_idx] {
+                        Some(pos) => pos,
+                        None => continue,
+                    };
+
+                    interp.buffer.set_cursor(pos);
                     for cmd in cmd_list {
                         let (cont, markmod) = cmd.invoke(interp)?;
                         if !cont {
@@ -415,7 +567,7 @@ impl Command {
 
             Nop(offset) => {
                 let line = offset
-                    .resolve_line(&interp.buffer)
+                    .resolve_line(&interp.buffer, &interp.env)
                     .ok_or(InvocationError::AddressNonResolvable)?;
                 interp.buffer.set_cursor(line);
                 Ok((true, MarkMod::Nil))
@@ -423,7 +575,7 @@ impl Command {
 
             Append(line_ref, Some(lines)) => {
                 let line = line_ref
-                    .resolve_line(&interp.buffer)
+                    .resolve_line(&interp.buffer, &interp.env)
                     .ok_or(InvocationError::AddressNonResolvable)?;
                 let delta = lines.len() as i64;
                 interp.buffer.append(line, lines.clone());
@@ -432,7 +584,7 @@ impl Command {
 
             Insert(line_ref, Some(lines)) => {
                 let line = line_ref
-                    .resolve_line(&interp.buffer)
+                    .resolve_line(&interp.buffer, &interp.env)
                     .ok_or(InvocationError::AddressNonResolvable)?;
                 let delta = lines.len() as i64;
                 interp.buffer.insert(line, lines.clone());
@@ -447,13 +599,96 @@ impl Command {
 
             Change(line_ref, Some(lines)) => {
                 let (start, end) = line_ref
-                    .resolve_range(&interp.buffer)
+                    .resolve_range(&interp.buffer, &interp.env)
                     .ok_or(InvocationError::AddressNonResolvable)?;
                 let delta = lines.len() as i64 - (1 + end - start) as i64;
                 interp.buffer.change(start, end, lines.clone());
                 Ok((true, MarkMod::After { start, delta }))
             }
 
+            Sort(addr, flags) => {
+                let (start, end) = addr
+                    .resolve_range(&interp.buffer, &interp.env)
+                    .ok_or(InvocationError::AddressNonResolvable)?;
+
+                let ignore_case = interp.env.ignore_case;
+                let markmod = sort::sort(&mut interp.buffer, start, end, flags, ignore_case)?;
+                interp.buffer.remap_marks(|line| markmod.moved(line));
+
+                Ok((true, markmod))
+            }
+
+            Uniq(addr, insensitive) => {
+                let (start, end) = addr
+                    .resolve_range(&interp.buffer, &interp.env)
+                    .ok_or(InvocationError::AddressNonResolvable)?;
+
+                let markmod = sort::uniq(&mut interp.buffer, start, end, *insensitive)?;
+                interp.buffer.remap_marks(|line| markmod.moved(line));
+
+                Ok((true, markmod))
+            }
+
+            Tac(addr) => {
+                let (start, end) = addr
+                    .resolve_range(&interp.buffer, &interp.env)
+                    .ok_or(InvocationError::AddressNonResolvable)?;
+
+                let markmod = sort::tac(&mut interp.buffer, start, end)?;
+                interp.buffer.remap_marks(|line| markmod.moved(line));
+
+                Ok((true, markmod))
+            }
+
+            Indent(addr, levels) => {
+                let (start, end) = addr
+                    .resolve_range(&interp.buffer, &interp.env)
+                    .ok_or(InvocationError::AddressNonResolvable)?;
+
+                shift::indent(&mut interp.buffer, start, end, interp.env.indent, *levels)?;
+
+                Ok((true, MarkMod::Nil))
+            }
+
+            Dedent(addr, levels) => {
+                let (start, end) = addr
+                    .resolve_range(&interp.buffer, &interp.env)
+                    .ok_or(InvocationError::AddressNonResolvable)?;
+
+                shift::dedent(&mut interp.buffer, start, end, interp.env.indent, *levels)?;
+
+                Ok((true, MarkMod::Nil))
+            }
+
+            Reflow(addr, width) => {
+                let (start, end) = addr
+                    .resolve_range(&interp.buffer, &interp.env)
+                    .ok_or(InvocationError::AddressNonResolvable)?;
+
+                let width = width.unwrap_or(interp.env.text_width);
+                let markmod = reflow::reflow(&mut interp.buffer, start, end, width)?;
+                interp.buffer.remap_marks(|line| markmod.moved(line));
+
+                Ok((true, markmod))
+            }
+
+            Align(addr, re, every) => {
+                let (start, end) = addr
+                    .resolve_range(&interp.buffer, &interp.env)
+                    .ok_or(InvocationError::AddressNonResolvable)?;
+
+                let re = match (re, &interp.env.last_re) {
+                    (Some(re), _) | (None, Some(re)) => re.clone(),
+                    (None, None) => return Err(InvocationError::MissingPattern),
+                };
+
+                let matcher = re.matcher(interp.env.ignore_case);
+                align::align(&mut interp.buffer, start, end, matcher, *every)?;
+                interp.env.last_re = Some(re);
+
+                Ok((true, MarkMod::Nil))
+            }
+
             NextBuffer => {
                 let filename = interp
                     .filelist
@@ -501,56 +736,230 @@ impl Command {
     }
 }
 
-fn run_subst(
-    buffer: &mut Buffer,
+pub(super) fn subst_args<S: ScratchPad>(
+    interp: &Interpreter<S>,
+    addr: &Address,
+    re: &Option<Re>,
+    pat: &Option<Pat>,
+    flags: &Option<SubstFlags>,
+) -> Result<(usize, usize, Re, Pat, SubstFlags), InvocationError> {
+    let (start, end) = addr
+        .resolve_range(&interp.buffer, &interp.env)
+        .ok_or(InvocationError::AddressNonResolvable)?;
+
+    let flags = flags.unwrap_or_else(|| {
+        if re.is_none() && pat.is_none() {
+            interp.env.last_flags.unwrap_or_default()
+        } else {
+            Default::default()
+        }
+    });
+
+    let re = match (re, &interp.env.last_re) {
+        (Some(re), _) | (None, Some(re)) => re.clone(),
+        (None, None) => return Err(InvocationError::MissingPattern),
+    };
+
+    let pat = match (pat, &interp.env.last_pat) {
+        (Some(Pat::Replay), None) | (None, None) => return Err(InvocationError::MissingPattern),
+        (Some(Pat::Replay), Some(pat)) | (Some(pat), _) | (None, Some(pat)) => pat.clone(),
+    };
+
+    Ok((start, end, re, pat, flags))
+}
+
+/// The lines within a resolved address, as long as it's a valid range to change
+pub(super) fn address_lines(
+    buffer: &Buffer,
     start: usize,
     end: usize,
-    re: &Re,
+) -> Result<Vec<String>, InvocationError> {
+    if start == 0 || end < start {
+        return Err(InvocationError::AddressNonResolvable);
+    }
+
+    buffer
+        .range(start, end)
+        .ok_or(InvocationError::AddressNonResolvable)
+}
+
+fn split_line(line: &str, re: &Regex, keep: bool) -> Vec<String> {
+    let mut pieces = Vec::new();
+    let mut last = 0;
+
+    for m in re.find_iter(line) {
+        if m.start() == m.end() {
+            continue;
+        }
+
+        let end = if keep { m.end() } else { m.start() };
+        pieces.push(line[last..end].to_string());
+        last = m.end();
+    }
+
+    if last < line.len() || pieces.is_empty() {
+        pieces.push(line[last..].to_string());
+    }
+
+    pieces
+}
+
+fn run_subst<S: ScratchPad>(
+    interp: &mut Interpreter<S>,
+    start: usize,
+    end: usize,
+    re: &Regex,
     pat: &Pat,
     flags: &SubstFlags,
-    scratch: &mut impl ScratchPad,
-) -> bool {
-    let mut replaced = false;
+) -> Result<bool, InvocationError> {
+    let filename = interp.env.filename.as_deref();
+    let first = interp.env.subst_count;
+    let mut count = first;
+    let mut overflow = None;
 
     if !pat.compatible(re) {
-        return false;
+        return Ok(false);
     }
 
     for i in start..=end {
-        let line = if let Some(line) = buffer.line(i) {
+        let line = if let Some(line) = interp.buffer.line(i) {
             line.clone()
         } else {
             continue;
         };
 
+        let before = count;
         let replaced = re
             .replacen(&line, flags.occurances, |cap: &Captures| {
-                replaced = true;
-                pat.expand(&cap)
+                let ctx = ExpandCtx {
+                    lineno: i,
+                    count,
+                    filename,
+                };
+
+                count += 1;
+                pat.expand(cap, &ctx).unwrap_or_else(|err| {
+                    overflow = Some(err);
+                    String::new()
+                })
             })
             .to_string();
 
+        if let Some(err) = overflow {
+            interp.env.subst_count = count;
+            return Err(err);
+        }
+
         if flags.print {
-            scratch.print(&replaced);
+            interp.scratch.print(&replaced);
+        }
+
+        if count > before {
+            interp.buffer.replace_line(i, replaced);
         }
+    }
+
+    interp.env.subst_count = count;
+
+    Ok(count > first)
+}
+
+fn run_translit(buffer: &mut Buffer, start: usize, end: usize, pairs: &[(char, char)]) -> bool {
+    let mut replaced = false;
+
+    for i in start..=end {
+        let line = if let Some(line) = buffer.line(i) {
+            line
+        } else {
+            continue;
+        };
+
+        let mapped: String = line
+            .chars()
+            .map(|ch| match pairs.iter().find(|(from, _)| *from == ch) {
+                Some((_, to)) => *to,
+                None => ch,
+            })
+            .collect();
 
-        buffer.replace_line(i, replaced);
+        if mapped != *line {
+            replaced = true;
+            buffer.replace_line(i, mapped);
+        }
     }
 
     replaced
 }
 
+fn confirm_subst<S: ScratchPad>(
+    interp: &mut Interpreter<S>,
+    start: usize,
+    end: usize,
+    re: &Re,
+    pat: &Pat,
+    flags: SubstFlags,
+) -> Result<bool, InvocationError> {
+    if !interp.scratch.can_confirm() {
+        return Err(InvocationError::CannotConfirm);
+    }
+
+    if !pat.compatible(re.matcher(interp.env.ignore_case)) {
+        return Ok(false);
+    }
+
+    let mut session = SubstSession::new(
+        &interp.buffer,
+        &interp.env,
+        (start, end),
+        re,
+        pat.clone(),
+        flags,
+    )
+    .counting_from(interp.env.subst_count);
+
+    while let Some((_, line, span)) = session.prompt() {
+        let answer = interp.scratch.confirm(&line, span);
+        session.answer(answer, &mut interp.buffer, &mut interp.scratch);
+    }
+
+    interp.env.subst_count = session.count();
+    interp.env.journal.confirmed(session.made());
+
+    match session.error() {
+        Some(err) => Err(err.clone()),
+        None => Ok(session.replaced()),
+    }
+}
+
 impl MarkMod {
-    pub fn modify(&self, marks: &mut [usize]) {
+    /// Moves the lines marked by a `g` to where they went, unmarking those which were removed
+    pub fn modify<'a>(&self, marks: impl IntoIterator<Item = &'a mut Option<usize>>) {
         if matches!(self, MarkMod::Nil) {
             return;
         }
 
         for mark in marks {
-            if self.check(*mark) {
-                self.diff(mark)
+            *mark = mark.and_then(|line| self.moved(line));
+        }
+    }
+
+    /// Where a line went, or nothing when it was removed
+    pub(crate) fn moved(&self, mut line: usize) -> Option<usize> {
+        if matches!(self, MarkMod::Nil) {
+            return Some(line);
+        }
+
+        if let MarkMod::Remap { start, end, to, .. } = self {
+            if *start <= line && line <= *end {
+                return to[line - start];
             }
         }
+
+        if self.check(line) {
+            self.diff(&mut line)
+        }
+
+        Some(line)
     }
 
     fn check(&self, check: usize) -> bool {
@@ -558,6 +967,7 @@ impl MarkMod {
             MarkMod::Nil => unreachable!(),
             MarkMod::After { start, .. } => *start < check,
             MarkMod::Range { start, end, .. } => *start < check && check <= *end,
+            MarkMod::Remap { end, .. } => *end < check,
         }
     }
 
@@ -566,6 +976,7 @@ impl MarkMod {
             MarkMod::Nil => unreachable!(),
             MarkMod::After { delta, .. } => *delta,
             MarkMod::Range { delta, .. } => *delta,
+            MarkMod::Remap { delta, .. } => *delta,
         };
 
         let mag = delta.abs() as usize;
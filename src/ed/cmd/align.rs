@@ -0,0 +1,124 @@
+use super::action::address_lines;
+use super::*;
+use regex::Regex;
+use unicode_width::UnicodeWidthStr;
+
+/// A line broken up around the delimiters it is aligned on
+struct Row<'a> {
+    fields: Vec<&'a str>,
+    delims: Vec<&'a str>,
+}
+
+/// Aligns the first (or every) match of a regex into columns, padding by display width.
+///
+/// Lines without a match are left as they are.
+pub(super) fn align(
+    buffer: &mut Buffer,
+    start: usize,
+    end: usize,
+    re: &Regex,
+    every: bool,
+) -> Result<(), InvocationError> {
+    let lines = address_lines(buffer, start, end)?;
+    let rows: Vec<Option<Row>> = lines.iter().map(|line| row(line, re, every)).collect();
+
+    let mut field_widths: Vec<usize> = Vec::new();
+    let mut delim_widths: Vec<usize> = Vec::new();
+
+    for row in rows.iter().flatten() {
+        for (i, delim) in row.delims.iter().enumerate() {
+            grow(&mut field_widths, i, row.fields[i].width());
+            grow(&mut delim_widths, i, delim.width());
+        }
+    }
+
+    let aligned = lines
+        .iter()
+        .zip(&rows)
+        .map(|(line, row)| match row {
+            Some(row) => render(row, &field_widths, &delim_widths),
+            None => line.clone(),
+        })
+        .collect();
+
+    buffer.change(start, end, aligned);
+    buffer.set_cursor(end);
+
+    Ok(())
+}
+
+fn row<'a>(line: &'a str, re: &Regex, every: bool) -> Option<Row<'a>> {
+    let mut fields = Vec::new();
+    let mut delims = Vec::new();
+    let mut last = 0;
+
+    for m in re.find_iter(line) {
+        if m.start() == m.end() {
+            continue;
+        }
+
+        let field = &line[last..m.start()];
+        fields.push(if last == 0 {
+            field.trim_end()
+        } else {
+            field.trim()
+        });
+        delims.push(m.as_str());
+        last = m.end();
+
+        if !every {
+            break;
+        }
+    }
+
+    if delims.is_empty() {
+        return None;
+    }
+
+    fields.push(line[last..].trim());
+
+    Some(Row { fields, delims })
+}
+
+fn render(row: &Row, field_widths: &[usize], delim_widths: &[usize]) -> String {
+    let mut out = String::new();
+
+    for (i, delim) in row.delims.iter().enumerate() {
+        let field = row.fields[i];
+        out.push_str(field);
+
+        if field_widths[i] > 0 {
+            pad(&mut out, field_widths[i] - field.width() + 1);
+        }
+
+        out.push_str(delim);
+        pad(&mut out, delim_widths[i] - delim.width());
+
+        let next = row.fields[i + 1];
+        let next_width = field_widths
+            .get(i + 1)
+            .copied()
+            .unwrap_or_else(|| next.width());
+
+        if next_width > 0 {
+            out.push(' ');
+        }
+    }
+
+    out.push_str(row.fields[row.fields.len() - 1]);
+    out.truncate(out.trim_end().len());
+
+    out
+}
+
+fn grow(widths: &mut Vec<usize>, i: usize, width: usize) {
+    if widths.len() <= i {
+        widths.resize(i + 1, 0);
+    }
+
+    widths[i] = widths[i].max(width);
+}
+
+fn pad(out: &mut String, n: usize) {
+    out.push_str(&" ".repeat(n));
+}
@@ -0,0 +1,341 @@
+use super::action::subst_args;
+use super::*;
+use crate::ed::addr::{Address, Offset, Point};
+use crate::ed::re::{ExpandCtx, Re};
+use crate::interp::{scratch::ScratchPad, Env, Interpreter};
+use regex::Regex;
+use std::ops::Range;
+
+/// A response to being asked whether to make a substitution
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Answer {
+    /// Make this substitution
+    Yes,
+    /// Skip this substitution
+    No,
+    /// Make this and every remaining substitution without asking
+    All,
+    /// Skip this and every remaining substitution
+    Quit,
+}
+
+/// A substitution which is confirmed match by match
+///
+/// Lines are visited in order, and a line is only rewritten once every match in it has been
+/// answered.
+#[derive(Debug)]
+pub struct SubstSession {
+    end: usize,
+    source: Re,
+    re: Regex,
+    pat: Pat,
+    flags: SubstFlags,
+    filename: Option<String>,
+
+    lineno: usize,
+    line: String,
+    matches: Vec<Range<usize>>,
+    accepted: Vec<bool>,
+    count: usize,
+
+    all: bool,
+    replaced: bool,
+    made: Vec<Command>,
+    error: Option<InvocationError>,
+}
+
+impl Command {
+    /// Begins a substitution that asks before each replacement when the command is a `s` with
+    /// the `c` flag.
+    pub(crate) fn subst_session<S: ScratchPad>(
+        &self,
+        interp: &mut Interpreter<S>,
+    ) -> Result<Option<SubstSession>, InvocationError> {
+        let (addr, re, pat, flags) = match self {
+            Command::Subst(addr, re, pat, flags) => (addr, re, pat, flags),
+            _ => return Ok(None),
+        };
+
+        let (start, end, re, pat, flags) = subst_args(interp, addr, re, pat, flags)?;
+
+        if !flags.confirm {
+            return Ok(None);
+        }
+
+        if !pat.compatible(re.matcher(interp.env.ignore_case)) {
+            return Err(InvocationError::DidNotReplace);
+        }
+
+        let session = SubstSession::new(
+            &interp.buffer,
+            &interp.env,
+            (start, end),
+            &re,
+            pat.clone(),
+            flags,
+        );
+
+        if session.is_done() {
+            return Err(InvocationError::DidNotReplace);
+        }
+
+        interp.env.last_re = Some(re);
+        interp.env.last_pat = Some(pat);
+        interp.env.last_flags = Some(flags);
+
+        Ok(Some(session))
+    }
+}
+
+impl Answer {
+    /// Interpret a key press or typed response
+    pub fn from_char(ch: char) -> Option<Answer> {
+        match ch.to_ascii_lowercase() {
+            'y' => Some(Answer::Yes),
+            'n' => Some(Answer::No),
+            'a' => Some(Answer::All),
+            'q' => Some(Answer::Quit),
+            _ => None,
+        }
+    }
+}
+
+impl SubstSession {
+    pub(crate) fn new(
+        buffer: &Buffer,
+        env: &Env,
+        (start, end): (usize, usize),
+        re: &Re,
+        pat: Pat,
+        flags: SubstFlags,
+    ) -> SubstSession {
+        let mut session = SubstSession {
+            end,
+            source: re.clone(),
+            re: re.matcher(env.ignore_case).clone(),
+            pat,
+            flags,
+            filename: env.filename.clone(),
+
+            lineno: start,
+            line: String::new(),
+            matches: Vec::new(),
+            accepted: Vec::new(),
+            count: 0,
+
+            all: false,
+            replaced: false,
+            made: Vec::new(),
+            error: None,
+        };
+
+        session.seek(buffer, start);
+
+        session
+    }
+
+    /// The line number, the line as it would look with the answers given so far, and the span of
+    /// the match being asked about.
+    pub fn prompt(&self) -> Option<(usize, String, Range<usize>)> {
+        let pos = self.accepted.len();
+        let span = self.matches.get(pos)?;
+
+        let (mut preview, last, _) = self.apply().ok()?;
+        let start = preview.len() + (span.start - last);
+        let end = start + span.len();
+        preview.push_str(&self.line[last..]);
+
+        Some((self.lineno, preview, start..end))
+    }
+
+    /// Answer the pending prompt
+    pub fn answer(&mut self, answer: Answer, buffer: &mut Buffer, scratch: &mut impl ScratchPad) {
+        if self.is_done() {
+            return;
+        }
+
+        match answer {
+            Answer::Yes => self.accepted.push(true),
+            Answer::No => self.accepted.push(false),
+            Answer::All => self.all = true,
+            Answer::Quit => {
+                self.accepted.resize(self.matches.len(), false);
+                self.commit(buffer, scratch);
+                self.matches.clear();
+                return;
+            }
+        }
+
+        if self.all {
+            self.accepted.resize(self.matches.len(), true);
+        }
+
+        if let Err(err) = self.apply() {
+            self.fail(err);
+            return;
+        }
+
+        while self.accepted.len() == self.matches.len() && !self.is_done() {
+            self.commit(buffer, scratch);
+            self.seek(buffer, self.lineno + 1);
+
+            if self.all {
+                self.accepted.resize(self.matches.len(), true);
+            }
+        }
+    }
+
+    /// There are no more matches to ask about
+    pub fn is_done(&self) -> bool {
+        self.matches.is_empty()
+    }
+
+    /// Whether any substitution has been made
+    pub fn replaced(&self) -> bool {
+        self.replaced
+    }
+
+    /// Commands making the same changes without asking, one for each line rewritten
+    ///
+    /// A line where every match was accepted is given the substitution itself, any other line is
+    /// changed to how it ended up.
+    pub fn made(&self) -> &[Command] {
+        &self.made
+    }
+
+    /// Why the session stopped early, leaving the line it was on as it was
+    pub fn error(&self) -> Option<&InvocationError> {
+        self.error.as_ref()
+    }
+
+    /// Count replacements on from those a command has already made
+    pub(crate) fn counting_from(mut self, count: usize) -> SubstSession {
+        self.count = count;
+        self
+    }
+
+    /// How many replacements have been made, counting from where the session started
+    pub(crate) fn count(&self) -> usize {
+        self.count
+    }
+
+    fn fail(&mut self, err: InvocationError) {
+        self.error = Some(err);
+        self.matches.clear();
+        self.accepted.clear();
+    }
+
+    fn seek(&mut self, buffer: &Buffer, from: usize) {
+        self.matches.clear();
+        self.accepted.clear();
+
+        for lineno in from..=self.end {
+            let line = match buffer.line(lineno) {
+                Some(line) => line,
+                None => break,
+            };
+
+            let limit = if self.flags.occurances == 0 {
+                usize::MAX
+            } else {
+                self.flags.occurances
+            };
+
+            let matches: Vec<Range<usize>> = self
+                .re
+                .find_iter(line)
+                .take(limit)
+                .map(|m| m.range())
+                .collect();
+
+            if !matches.is_empty() {
+                self.lineno = lineno;
+                self.line = line.to_string();
+                self.matches = matches;
+                return;
+            }
+        }
+    }
+
+    /// The line up to the last answered match with the accepted ones replaced, where that
+    /// leaves off, and how many replacements were made.
+    fn apply(&self) -> Result<(String, usize, usize), InvocationError> {
+        self.replace(&self.accepted, self.count)
+    }
+
+    /// Like `apply`, for the given answers and counting on from `from`
+    fn replace(
+        &self,
+        answers: &[bool],
+        from: usize,
+    ) -> Result<(String, usize, usize), InvocationError> {
+        let mut buf = String::with_capacity(self.line.len());
+        let mut last = 0;
+        let mut count = from;
+
+        for (cap, accepted) in self.re.captures_iter(&self.line).zip(answers) {
+            let span = cap.get(0).unwrap().range();
+            buf.push_str(&self.line[last..span.start]);
+
+            if *accepted {
+                let ctx = ExpandCtx {
+                    lineno: self.lineno,
+                    count,
+                    filename: self.filename.as_deref(),
+                };
+
+                buf.push_str(&self.pat.expand(&cap, &ctx)?);
+                count += 1;
+            } else {
+                buf.push_str(&self.line[span.clone()]);
+            }
+
+            last = span.end;
+        }
+
+        Ok((buf, last, count - from))
+    }
+
+    fn commit(&mut self, buffer: &mut Buffer, scratch: &mut impl ScratchPad) {
+        if !self.accepted.iter().any(|accepted| *accepted) {
+            return;
+        }
+
+        let (mut line, last, count) = match self.apply() {
+            Ok(applied) => applied,
+            Err(err) => return self.fail(err),
+        };
+        line.push_str(&self.line[last..]);
+        self.count += count;
+
+        if self.flags.print {
+            scratch.print(&line);
+        }
+
+        let every = vec![true; self.matches.len()];
+        let plain = self.replace(&every, 0).ok().map(|(mut plain, last, _)| {
+            plain.push_str(&self.line[last..]);
+            plain
+        });
+
+        let addr = Address::Line(Offset::Nil(Point::Abs(self.lineno)));
+        self.made.push(if plain.as_ref() == Some(&line) {
+            let flags = SubstFlags {
+                confirm: false,
+                ..self.flags
+            };
+
+            Command::Subst(
+                addr,
+                Some(self.source.clone()),
+                Some(self.pat.clone()),
+                Some(flags),
+            )
+        } else {
+            Command::Change(addr, Some(vec![line.clone()]))
+        });
+
+        buffer.replace_line(self.lineno, line);
+        self.replaced = true;
+    }
+}
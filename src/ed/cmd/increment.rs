@@ -0,0 +1,194 @@
+use super::action::address_lines;
+use super::*;
+use std::ops::Range;
+
+/// A number found in a line, along with how it was written
+///
+/// The span of a hex or binary number leaves out its prefix.
+#[derive(Debug)]
+struct Number {
+    span: Range<usize>,
+    kind: Kind,
+}
+
+#[derive(Debug)]
+enum Kind {
+    Dec {
+        /// Nothing when the number is too large to hold
+        value: Option<i64>,
+        width: usize,
+    },
+    Hex {
+        value: u64,
+        width: usize,
+        upper: bool,
+    },
+    Bin {
+        value: u64,
+        width: usize,
+    },
+}
+
+/// Adds an amount to the nth number of each line, or a growing multiple of it when sequential.
+///
+/// Zero padding, hex and binary notation are kept.  No line is changed when any number would
+/// go out of range.
+pub(super) fn increment(
+    buffer: &mut Buffer,
+    start: usize,
+    end: usize,
+    amount: i64,
+    flags: IncFlags,
+) -> Result<bool, InvocationError> {
+    let lines = address_lines(buffer, start, end)?;
+    let mut changes = Vec::new();
+
+    for (i, line) in lines.iter().enumerate() {
+        let number = match numbers(line).nth(flags.nth - 1) {
+            Some(number) => number,
+            None => continue,
+        };
+
+        let step = if flags.sequential {
+            amount.checked_mul(changes.len() as i64 + 1)
+        } else {
+            Some(amount)
+        };
+
+        let value = step
+            .and_then(|step| number.kind.add(step))
+            .ok_or(InvocationError::NumberOverflow)?;
+
+        let mut replaced = line[..number.span.start].to_string();
+        replaced.push_str(&value);
+        replaced.push_str(&line[number.span.end..]);
+
+        changes.push((start + i, replaced));
+    }
+
+    let changed = !changes.is_empty();
+
+    for (lineno, line) in changes {
+        buffer.replace_line(lineno, line);
+    }
+
+    buffer.set_cursor(end);
+
+    Ok(changed)
+}
+
+impl Kind {
+    /// The number written the same way with an amount added, or nothing when it would go out
+    /// of range
+    fn add(&self, amount: i64) -> Option<String> {
+        match *self {
+            Kind::Dec { value, width } => {
+                let value = value?.checked_add(amount)?;
+                let sign = if value < 0 { "-" } else { "" };
+
+                Some(format!(
+                    "{}{:0width$}",
+                    sign,
+                    value.unsigned_abs(),
+                    width = width
+                ))
+            }
+
+            Kind::Hex {
+                value,
+                width,
+                upper,
+            } => {
+                let value = add_unsigned(value, amount)?;
+
+                if upper {
+                    Some(format!("{:0width$X}", value, width = width))
+                } else {
+                    Some(format!("{:0width$x}", value, width = width))
+                }
+            }
+
+            Kind::Bin { value, width } => {
+                let value = add_unsigned(value, amount)?;
+
+                Some(format!("{:0width$b}", value, width = width))
+            }
+        }
+    }
+}
+
+fn add_unsigned(value: u64, amount: i64) -> Option<u64> {
+    if amount < 0 {
+        value.checked_sub(amount.unsigned_abs())
+    } else {
+        value.checked_add(amount as u64)
+    }
+}
+
+/// The numbers in a line, from left to right
+fn numbers(line: &str) -> impl Iterator<Item = Number> + '_ {
+    let mut pos = 0;
+
+    std::iter::from_fn(move || {
+        let offset = line[pos..].find(|ch: char| ch.is_ascii_digit())?;
+        let digits = pos + offset;
+        let number = number_at(line, digits);
+
+        pos = number.span.end;
+        Some(number)
+    })
+}
+
+fn number_at(line: &str, digits: usize) -> Number {
+    let rest = &line[digits..];
+    let prefixed = |prefix: &str, radix: u32| {
+        let body = rest.strip_prefix(prefix).or_else(|| {
+            let upper = prefix.to_uppercase();
+            rest.strip_prefix(upper.as_str())
+        })?;
+
+        let len = body
+            .find(|ch: char| !ch.is_digit(radix))
+            .unwrap_or(body.len());
+        let value = u64::from_str_radix(&body[..len], radix).ok()?;
+
+        Some((&body[..len], value))
+    };
+
+    if let Some((body, value)) = prefixed("0x", 16) {
+        return Number {
+            span: digits + 2..digits + 2 + body.len(),
+            kind: Kind::Hex {
+                value,
+                width: body.len(),
+                upper: body.chars().any(|ch| ch.is_ascii_uppercase()),
+            },
+        };
+    }
+
+    if let Some((body, value)) = prefixed("0b", 2) {
+        return Number {
+            span: digits + 2..digits + 2 + body.len(),
+            kind: Kind::Bin {
+                value,
+                width: body.len(),
+            },
+        };
+    }
+
+    let len = rest
+        .find(|ch: char| !ch.is_ascii_digit())
+        .unwrap_or(rest.len());
+    let body = &rest[..len];
+
+    let negative = line[..digits].ends_with('-');
+    let start = if negative { digits - 1 } else { digits };
+    let width = if body.starts_with('0') { body.len() } else { 0 };
+
+    let value = line[start..digits + len].parse::<i64>().ok();
+
+    Number {
+        span: start..digits + len,
+        kind: Kind::Dec { value, width },
+    }
+}
@@ -1,12 +1,21 @@
-use crate::{ed::prelude::*, Buffer};
+use crate::{ed::prelude::*, interp::Env, Buffer, Setting};
+use serde::Serialize;
+
+pub use confirm::{Answer, SubstSession};
 
 mod action;
+mod align;
+mod confirm;
+mod increment;
+mod reflow;
+mod shift;
+mod sort;
 
 #[cfg(test)]
 mod test;
 
 /// A command to run on a buffer
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[cfg_attr(test, derive(PartialEq))]
 pub enum Command {
     /// Print lines within an address
@@ -24,6 +33,11 @@ pub enum Command {
     Mark(Offset, char),
     /// Join the lines over an address into one line
     Join(Address),
+    /// Join the lines over an address into one line, putting a separator between them
+    JoinWith(Address, String),
+    /// Split the lines in an address at each match of a regex, optionally keeping the match at
+    /// the end of each piece.  A match ending a line leaves no empty piece after it.
+    Split(Address, Option<Re>, bool),
     /// Move a set of lines to a point in the buffer
     Move(Address, Offset),
     /// Copy a set of lines to a point in the bufffer
@@ -67,6 +81,47 @@ pub enum Command {
     Insert(Offset, Option<Vec<String>>),
     /// Change s set of lines to a different set of lines
     Change(Address, Option<Vec<String>>),
+
+    /// Sort the lines within an address
+    Sort(Address, SortFlags),
+    /// Remove adjacent duplicate lines within an address, optionally ignoring case
+    Uniq(Address, bool),
+    /// Reverse the order of the lines within an address
+    Tac(Address),
+
+    /// Indent the lines within an address a number of levels
+    Indent(Address, usize),
+    /// Dedent the lines within an address a number of levels
+    Dedent(Address, usize),
+
+    /// Rewrap the paragraphs within an address to a width (or the default text width)
+    Reflow(Address, Option<usize>),
+
+    /// Align the first (or every) match of a regex within an address into columns
+    Align(Address, Option<Re>, bool),
+
+    /// Map characters within an address from one set onto another
+    Translit(Address, Vec<(char, char)>),
+
+    /// Add an amount to a number on each line within an address
+    Increment(Address, i64, IncFlags),
+
+    /// A place in a script to branch to
+    Label(String),
+    /// Branch to a label (or the end of a script) when the address resolves and, if asked, a
+    /// substitution has succeeded since the last such branch
+    Branch(Option<Address>, bool, Option<String>),
+
+    /// Run the commands in a file or from the output of a system command
+    Source(SysPoint),
+
+    /// Write the commands typed this session out as a script to a file or system command
+    Journal(SysPoint),
+
+    /// Change a setting to a value
+    Set(Setting, String),
+    /// Print one setting, or every setting, as `name=value`
+    Settings(Option<Setting>),
 }
 
 /// Additional flags to the subst command
@@ -76,20 +131,89 @@ pub struct SubstFlags {
     pub print: bool,
     /// The number of substitutions to make.  (0 is inifite)
     pub occurances: usize,
+    /// Whether or not to ask before making each substitution
+    pub confirm: bool,
+}
+
+/// Additional flags to the inc and dec commands
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct IncFlags {
+    /// Add the amount once more for each line changed, numbering them in sequence
+    pub sequential: bool,
+    /// Which number on a line to change, counting from 1
+    pub nth: usize,
 }
 
-#[derive(Debug, PartialEq)]
+impl Default for IncFlags {
+    fn default() -> IncFlags {
+        IncFlags {
+            sequential: false,
+            nth: 1,
+        }
+    }
+}
+
+/// Additional flags to the sort command
+#[derive(Debug, Default, Clone)]
+#[cfg_attr(test, derive(PartialEq))]
+pub struct SortFlags {
+    /// Sort in descending order
+    pub reverse: bool,
+    /// Compare by the first integer found instead of the text
+    pub numeric: bool,
+    /// Compare without regard to case
+    pub insensitive: bool,
+    /// Only keep the first of lines comparing equal
+    pub unique: bool,
+    /// Compare by the first capture (or whole match) of a regex instead of the whole line
+    pub key: Option<Re>,
+}
+
+/// Why a command failed to run
+#[derive(Debug, PartialEq, Clone, Serialize)]
 pub enum InvocationError {
+    /// An address refers to lines outside the buffer or a regex matched no line
     AddressNonResolvable,
+    /// A file could not be read
     ReadFile,
+    /// There is no next or previous file in the arglist
     ArgFetch,
+    /// A substitution reused a regex or pattern when there was none before
     MissingPattern,
+    /// A substitution matched nothing
     DidNotReplace,
+    /// The buffer has no file name to write to
     MissingFilename,
+    /// A system command failed or a write did not go through
     FailedCommand,
+    /// Text could not be put at the lines given
     InvalidInsertion,
+    /// The file or command to source could not be read
     UnableToSource,
+    /// A command was given somewhere it can not send its output
     InvalidTarget,
+    /// A branch was run outside a script
+    BranchOutsideScript,
+    /// A branch names a label the script doesn't have
+    UnknownLabel,
+    /// A script took more branches than the loop limit
+    LoopLimit,
+    /// A file sources itself, directly or not
+    RecursiveSource,
+    /// A setting was given a value it can't take
+    InvalidValue,
+    /// A command would write files or run programs where that isn't allowed, as in a dry run
+    Sandboxed,
+    /// A number changed by `inc` or `dec` would go out of range, or already was
+    NumberOverflow,
+    /// A counter in a substitution went past the largest (or smallest) number it can hold
+    CounterOverflow,
+    /// A substitution would ask before each replacement where there is no one to ask
+    CannotConfirm,
+    /// A line of a script (counted from 1) failed to parse
+    SourceParse(usize),
+    /// A command on a line of a script (counted from 1) failed
+    SourceLine(usize, Box<InvocationError>),
 }
 
 impl Command {
@@ -101,6 +225,17 @@ impl Command {
         matches!(self, Append(_, None) | Insert(_, None) | Change(_, None))
     }
 
+    /// Whether a command still makes sense when replayed on another file, which is not the case
+    /// for commands writing, quitting or moving between files
+    pub fn is_replayable(&self) -> bool {
+        use Command::*;
+
+        !matches!(
+            self,
+            Write(..) | Edit(_) | NextBuffer | PrevBuffer | Quit | Journal(_)
+        )
+    }
+
     /// Injects text into a command
     ///
     /// Only works if `cmd.needs_text()` returns true otherwise it panics.
@@ -116,12 +251,64 @@ impl Command {
         };
     }
 
-    pub(crate) fn text_markers(&self, buffer: &Buffer) -> Option<(usize, usize)> {
+    /// Whether a command reaches outside the buffer, writing files or running programs
+    pub(crate) fn has_side_effects(&self) -> bool {
+        use Command::*;
+
+        matches!(
+            self,
+            Write(..)
+                | Run(_)
+                | Edit(_)
+                | Journal(_)
+                | Read(_, SysPoint::Command(_))
+                | Source(SysPoint::Command(_))
+        )
+    }
+
+    /// Which commands of a script are substitutions a `bt` tests, being followed by it with
+    /// only other substitutions in between
+    pub(crate) fn tested_substitutions(cmds: &[Command]) -> Vec<bool> {
+        let mut tested = vec![false; cmds.len()];
+
+        for pos in (0..cmds.len()).rev() {
+            if !cmds[pos].substitutes() {
+                continue;
+            }
+
+            tested[pos] = match cmds.get(pos + 1) {
+                Some(Command::Branch(_, true, _)) => true,
+                Some(next) => next.substitutes() && tested[pos + 1],
+                None => false,
+            };
+        }
+
+        tested
+    }
+
+    /// A substitution, or a `g` or `v` running only substitutions
+    fn substitutes(&self) -> bool {
         match self {
-            Command::Append(line, None) => line.resolve_line(buffer).map(|line| (line + 1, 0)),
-            Command::Insert(line, None) => line.resolve_line(buffer).map(|line| (line, 0)),
+            Command::Subst(..) => true,
+            Command::Global(_, _, cmds) | Command::Void(_, _, cmds) => {
+                cmds.iter().all(|cmd| matches!(cmd, Command::Subst(..)))
+            }
+            _ => false,
+        }
+    }
+
+    /// Where a label is defined within a script
+    pub(crate) fn find_label(cmds: &[Command], label: &str) -> Option<usize> {
+        cmds.iter()
+            .position(|cmd| matches!(cmd, Command::Label(name) if name == label))
+    }
+
+    pub(crate) fn text_markers(&self, buffer: &Buffer, env: &Env) -> Option<(usize, usize)> {
+        match self {
+            Command::Append(line, None) => line.resolve_line(buffer, env).map(|line| (line + 1, 0)),
+            Command::Insert(line, None) => line.resolve_line(buffer, env).map(|line| (line, 0)),
             Command::Change(addr, None) => addr
-                .resolve_range(buffer)
+                .resolve_range(buffer, env)
                 .map(|(line, hide)| (line, hide - line)),
 
             _ => None,
@@ -134,6 +321,7 @@ impl Default for SubstFlags {
         SubstFlags {
             print: false,
             occurances: 1,
+            confirm: false,
         }
     }
 }
@@ -151,6 +339,19 @@ impl std::fmt::Display for InvocationError {
             InvocationError::InvalidInsertion => write!(fmt, "invalid line insertion"),
             InvocationError::UnableToSource => write!(fmt, "unable to read source"),
             InvocationError::InvalidTarget => write!(fmt, "invalid target"),
+            InvocationError::BranchOutsideScript => write!(fmt, "can only branch in a script"),
+            InvocationError::UnknownLabel => write!(fmt, "unknown label"),
+            InvocationError::LoopLimit => write!(fmt, "too many branches taken"),
+            InvocationError::RecursiveSource => write!(fmt, "source includes itself"),
+            InvocationError::InvalidValue => write!(fmt, "invalid value for setting"),
+            InvocationError::Sandboxed => {
+                write!(fmt, "command may not write files or run programs here")
+            }
+            InvocationError::NumberOverflow => write!(fmt, "number out of range"),
+            InvocationError::CounterOverflow => write!(fmt, "counter overflowed"),
+            InvocationError::CannotConfirm => write!(fmt, "unable to confirm substitutions here"),
+            InvocationError::SourceParse(line) => write!(fmt, "line {} failed to parse", line),
+            InvocationError::SourceLine(line, err) => write!(fmt, "line {}: {}", line, err),
         }
     }
 }
@@ -158,16 +359,44 @@ impl std::fmt::Display for InvocationError {
 impl Command {
     /// Extracts a list of commands from a string or gives what command fails to parse and the line number
     pub fn from_content(content: &str) -> Result<Vec<Command>, (&str, usize)> {
+        let cmds = Command::from_content_lines(content)?;
+
+        Ok(cmds.into_iter().map(|(_, cmd)| cmd).collect())
+    }
+
+    /// Like `from_content` but keeps the (0-based) line each command came from
+    pub(crate) fn from_content_lines(
+        content: &str,
+    ) -> Result<Vec<(usize, Command)>, (&str, usize)> {
         let mut lines = content.split("\n").enumerate();
         let mut cmds = Vec::new();
+        let mut origins = Vec::new();
+
+        while let Some((pos, origin)) = lines.next() {
+            let mut line = origin;
 
-        while let Some((pos, mut line)) = lines.next() {
-            let origin = line;
+            // a comment is never carried on, even when it ends in a backslash
+            if origin.trim_start().starts_with('#') {
+                continue;
+            }
 
-            if let Some(pos) = line.find('#') {
-                if line[..pos].trim().is_empty() {
-                    continue;
+            // a trailing backslash carries a command list onto the next line, as at the prompt
+            let joined;
+            if origin.ends_with('\\') {
+                let mut buf = origin.to_string();
+
+                while buf.ends_with('\\') {
+                    match lines.next() {
+                        Some((_, next)) => {
+                            buf.push('\n');
+                            buf.push_str(next);
+                        }
+                        None => return Err((origin, pos)),
+                    }
                 }
+
+                joined = buf;
+                line = &joined;
             }
 
             line = line.trim();
@@ -202,21 +431,33 @@ impl Command {
                 }
             }
 
+            origins.extend(new_commands.iter().map(|_| (origin, pos)));
             cmds.extend(new_commands);
         }
 
-        Ok(cmds)
+        for (cmd, origin) in cmds.iter().zip(&origins) {
+            if let Command::Branch(_, _, Some(label)) = cmd {
+                if Command::find_label(&cmds, label).is_none() {
+                    return Err(*origin);
+                }
+            }
+        }
+
+        Ok(origins.into_iter().map(|(_, pos)| pos).zip(cmds).collect())
     }
 
-    /// Parse commands from one string
-    pub fn from_expr(mut content: &str) -> Result<Vec<Command>, &str> {
-        if let Some(pos) = content.find('#') {
-            content = content.split_at(pos).0;
+    /// Parse commands from one string, where a `#` after the commands or in place of one
+    /// starts a comment
+    pub fn from_expr(content: &str) -> Result<Vec<Command>, &str> {
+        if content.trim_start().starts_with('#') {
+            return Ok(Vec::new());
         }
 
         let (rest, cmds) = Command::parse_multi(content).or(Err(content))?;
+        let rest = rest.trim_start();
+        let rest = rest.strip_prefix('|').unwrap_or(rest).trim_start();
 
-        if !rest.trim().is_empty() {
+        if !rest.is_empty() && !rest.starts_with('#') {
             return Err(content);
         }
 
@@ -0,0 +1,122 @@
+use super::action::{address_lines, MarkMod};
+use super::*;
+
+/// Characters which may make up a prefix repeated on every line of a paragraph, such as `// `,
+/// `# ` or `> `
+const PREFIX_CHARS: &[char] = &[' ', '\t', '/', '#', '>', ';', '!', '%'];
+
+pub(super) fn reflow(
+    buffer: &mut Buffer,
+    start: usize,
+    end: usize,
+    width: usize,
+) -> Result<MarkMod, InvocationError> {
+    let lines = address_lines(buffer, start, end)?;
+
+    let mut to = Vec::with_capacity(lines.len());
+    let mut wrapped: Vec<String> = Vec::with_capacity(lines.len());
+    let mut pos = 0;
+
+    while pos < lines.len() {
+        if is_blank(&lines[pos]) {
+            to.push(Some(start + wrapped.len()));
+            wrapped.push(lines[pos].clone());
+            pos += 1;
+            continue;
+        }
+
+        let len = lines[pos..]
+            .iter()
+            .position(|line| is_blank(line))
+            .unwrap_or(lines.len() - pos);
+
+        let paragraph = &lines[pos..pos + len];
+        let (rewrapped, lineno) = wrap(paragraph, width);
+
+        to.extend(lineno.iter().map(|i| Some(start + wrapped.len() + i)));
+        wrapped.extend(rewrapped);
+        pos += len;
+    }
+
+    let delta = wrapped.len() as i64 - lines.len() as i64;
+    let last = start + wrapped.len() - 1;
+
+    buffer.change(start, end, wrapped);
+    buffer.set_cursor(last);
+
+    Ok(MarkMod::Remap {
+        start,
+        end,
+        to,
+        delta,
+    })
+}
+
+/// Wraps a paragraph, giving the new lines and which of them each old line's first word ended up
+/// on.
+fn wrap(paragraph: &[String], width: usize) -> (Vec<String>, Vec<usize>) {
+    let prefix = common_prefix(paragraph);
+
+    let mut lines = Vec::new();
+    let mut lineno = Vec::with_capacity(paragraph.len());
+    let mut current = String::new();
+
+    for line in paragraph {
+        lineno.push(lines.len());
+        let mut first = true;
+
+        for word in line[prefix.len()..].split_whitespace() {
+            let len = current.chars().count();
+
+            if len == 0 {
+                current.push_str(&prefix);
+                current.push_str(word);
+            } else if len + 1 + word.chars().count() <= width {
+                current.push(' ');
+                current.push_str(word);
+            } else {
+                lines.push(std::mem::take(&mut current));
+                current.push_str(&prefix);
+                current.push_str(word);
+            }
+
+            if first {
+                *lineno.last_mut().unwrap() = lines.len();
+                first = false;
+            }
+        }
+    }
+
+    if !current.is_empty() || lines.is_empty() {
+        lines.push(current);
+    }
+
+    (lines, lineno)
+}
+
+fn prefix_of(line: &str) -> &str {
+    let len = line.len() - line.trim_start_matches(PREFIX_CHARS).len();
+    &line[..len]
+}
+
+fn is_blank(line: &str) -> bool {
+    prefix_of(line).len() == line.len()
+}
+
+fn common_prefix(paragraph: &[String]) -> String {
+    let mut prefix = prefix_of(&paragraph[0]);
+
+    for line in &paragraph[1..] {
+        let other = prefix_of(line);
+        let len = prefix
+            .char_indices()
+            .zip(other.chars())
+            .find(|((_, a), b)| a != b)
+            .map(|((i, _), _)| i)
+            .unwrap_or_else(|| prefix.len().min(other.len()));
+
+        prefix = &prefix[..len];
+    }
+
+    prefix.to_string()
+}
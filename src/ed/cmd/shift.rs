@@ -0,0 +1,70 @@
+use super::action::address_lines;
+use super::*;
+use crate::interp::Indent;
+
+pub(super) fn indent(
+    buffer: &mut Buffer,
+    start: usize,
+    end: usize,
+    indent: Indent,
+    levels: usize,
+) -> Result<(), InvocationError> {
+    let unit = match indent {
+        Indent::Tabs => "\t".to_string(),
+        Indent::Spaces(width) => " ".repeat(width),
+    };
+
+    let prefix = unit.repeat(levels);
+
+    shift(buffer, start, end, |line| format!("{}{}", prefix, line))
+}
+
+pub(super) fn dedent(
+    buffer: &mut Buffer,
+    start: usize,
+    end: usize,
+    indent: Indent,
+    levels: usize,
+) -> Result<(), InvocationError> {
+    let width = match indent {
+        Indent::Tabs => 1,
+        Indent::Spaces(width) => width.max(1),
+    };
+
+    shift(buffer, start, end, |mut line| {
+        for _ in 0..levels {
+            line = if let Some(rest) = line.strip_prefix('\t') {
+                rest
+            } else {
+                let spaces = line.len() - line.trim_start_matches(' ').len();
+                &line[spaces.min(width)..]
+            };
+        }
+
+        line.to_string()
+    })
+}
+
+/// Rewrites each non blank line in an address
+fn shift(
+    buffer: &mut Buffer,
+    start: usize,
+    end: usize,
+    f: impl Fn(&str) -> String,
+) -> Result<(), InvocationError> {
+    let lines = address_lines(buffer, start, end)?
+        .into_iter()
+        .map(|line| {
+            if line.trim().is_empty() {
+                line
+            } else {
+                f(&line)
+            }
+        })
+        .collect();
+
+    buffer.change(start, end, lines);
+    buffer.set_cursor(end);
+
+    Ok(())
+}
@@ -0,0 +1,133 @@
+use super::action::{address_lines, MarkMod};
+use super::*;
+use std::cmp::Ordering;
+
+impl SortFlags {
+    fn key<'a>(&self, line: &'a str, ignore_case: bool) -> Option<&'a str> {
+        let re = match &self.key {
+            Some(re) => re,
+            None => return Some(line),
+        };
+
+        let cap = re.matcher(ignore_case).captures(line)?;
+        cap.get(1).or_else(|| cap.get(0)).map(|m| m.as_str())
+    }
+
+    fn compare(&self, a: &str, b: &str, ignore_case: bool) -> Ordering {
+        let (a, b) = (self.key(a, ignore_case), self.key(b, ignore_case));
+
+        let ord = if self.numeric {
+            a.and_then(first_number).cmp(&b.and_then(first_number))
+        } else if self.insensitive {
+            a.map(str::to_lowercase).cmp(&b.map(str::to_lowercase))
+        } else {
+            a.cmp(&b)
+        };
+
+        if self.reverse {
+            ord.reverse()
+        } else {
+            ord
+        }
+    }
+}
+
+pub(super) fn sort(
+    buffer: &mut Buffer,
+    start: usize,
+    end: usize,
+    flags: &SortFlags,
+    ignore_case: bool,
+) -> Result<MarkMod, InvocationError> {
+    let lines = address_lines(buffer, start, end)?;
+
+    let mut order: Vec<usize> = (0..lines.len()).collect();
+    order.sort_by(|a, b| flags.compare(&lines[*a], &lines[*b], ignore_case));
+
+    Ok(rearrange(buffer, start, lines, order, |a, b| {
+        flags.unique && flags.compare(a, b, ignore_case) == Ordering::Equal
+    }))
+}
+
+pub(super) fn uniq(
+    buffer: &mut Buffer,
+    start: usize,
+    end: usize,
+    insensitive: bool,
+) -> Result<MarkMod, InvocationError> {
+    let lines = address_lines(buffer, start, end)?;
+    let order = (0..lines.len()).collect();
+
+    Ok(rearrange(buffer, start, lines, order, |a, b| {
+        if insensitive {
+            a.to_lowercase() == b.to_lowercase()
+        } else {
+            a == b
+        }
+    }))
+}
+
+pub(super) fn tac(
+    buffer: &mut Buffer,
+    start: usize,
+    end: usize,
+) -> Result<MarkMod, InvocationError> {
+    let lines = address_lines(buffer, start, end)?;
+    let order = (0..lines.len()).rev().collect();
+
+    Ok(rearrange(buffer, start, lines, order, |_, _| false))
+}
+
+/// Places lines (starting at `start`) in the given order, dropping any line which is the `same`
+/// as the one kept before it.  Dropped lines are unmarked.
+fn rearrange(
+    buffer: &mut Buffer,
+    start: usize,
+    lines: Vec<String>,
+    order: Vec<usize>,
+    same: impl Fn(&str, &str) -> bool,
+) -> MarkMod {
+    let end = start + lines.len() - 1;
+    let mut to = vec![None; lines.len()];
+    let mut kept: Vec<usize> = Vec::with_capacity(order.len());
+
+    for idx in order {
+        match kept.last() {
+            Some(prev) if same(&lines[*prev], &lines[idx]) => {}
+            _ => {
+                to[idx] = Some(start + kept.len());
+                kept.push(idx);
+            }
+        }
+    }
+
+    let delta = kept.len() as i64 - lines.len() as i64;
+    let mut lines: Vec<Option<String>> = lines.into_iter().map(Some).collect();
+    let sorted = kept.iter().filter_map(|idx| lines[*idx].take()).collect();
+
+    buffer.change(start, end, sorted);
+    buffer.set_cursor(start + kept.len() - 1);
+
+    MarkMod::Remap {
+        start,
+        end,
+        to,
+        delta,
+    }
+}
+
+/// The first (possibly negative) integer in a line
+fn first_number(line: &str) -> Option<i128> {
+    let digits = line.find(|ch: char| ch.is_ascii_digit())?;
+    let len = line[digits..]
+        .find(|ch: char| !ch.is_ascii_digit())
+        .unwrap_or(line.len() - digits);
+
+    let start = if line[..digits].ends_with('-') {
+        digits - 1
+    } else {
+        digits
+    };
+
+    line[start..digits + len].parse().ok()
+}
@@ -96,6 +96,163 @@ fn bar() -> usize {
     }
 }
 
+mod join_with {
+    use super::*;
+
+    #[test]
+    fn separator() {
+        let cmd = Command::from_str("1,3j ', '").expect("should parse");
+
+        let mut interp =
+            Interpreter::from_reader::<StdoutScratchPad, &[u8]>("a\n b\nc\nd\n".as_bytes())
+                .expect("should read");
+
+        assert_eq!(Ok(true), interp.exec(&cmd));
+
+        assert_content!(interp.buffer, "a,  b, c\nd\n");
+    }
+
+    #[test]
+    fn global() {
+        let cmd = Command::from_str("g/^-/-,.j ''").expect("should parse");
+
+        let mut interp =
+            Interpreter::from_reader::<StdoutScratchPad, &[u8]>("a\n-b\nc\n-d\n-e\n".as_bytes())
+                .expect("should read");
+
+        assert_eq!(Ok(true), interp.exec(&cmd));
+
+        assert_content!(interp.buffer, "a-b\nc-d-e\n");
+    }
+
+    #[test]
+    fn global_joined_away() {
+        let cmd = Command::from_str("g/x/.,+1j\",\"").expect("should parse");
+
+        let mut interp =
+            Interpreter::from_reader::<StdoutScratchPad, &[u8]>("x1\nx2\ny\nx3\nz\n".as_bytes())
+                .expect("should read");
+
+        assert_eq!(Ok(true), interp.exec(&cmd));
+
+        assert_content!(interp.buffer, "x1,x2\ny\nx3,z\n");
+    }
+}
+
+mod split {
+    use super::*;
+
+    #[test]
+    fn drop_delimiter() {
+        let cmd = Command::from_str("split/, */").expect("should parse");
+
+        let mut interp = Interpreter::from_reader::<StdoutScratchPad, &[u8]>("a, b,c\n".as_bytes())
+            .expect("should read");
+
+        assert_eq!(Ok(true), interp.exec(&cmd));
+
+        assert_content!(interp.buffer, "a\nb\nc\n");
+        assert_eq!(interp.buffer.cursor(), 3);
+    }
+
+    #[test]
+    fn keep_delimiter() {
+        let cmd = Command::from_str("%split/;/k").expect("should parse");
+
+        let mut interp =
+            Interpreter::from_reader::<StdoutScratchPad, &[u8]>("a;b\nc;d;e\n".as_bytes())
+                .expect("should read");
+
+        assert_eq!(Ok(true), interp.exec(&cmd));
+
+        assert_content!(interp.buffer, "a;\nb\nc;\nd;\ne\n");
+    }
+
+    #[test]
+    fn global() {
+        let cmd = Command::from_str("g/,/split/,/").expect("should parse");
+
+        let mut interp =
+            Interpreter::from_reader::<StdoutScratchPad, &[u8]>("a,b\nc\nd,e,f\n".as_bytes())
+                .expect("should read");
+
+        assert_eq!(Ok(true), interp.exec(&cmd));
+
+        assert_content!(interp.buffer, "a\nb\nc\nd\ne\nf\n");
+    }
+
+    #[test]
+    fn trailing_delimiter() {
+        let cmd = Command::from_str("%split/,/").expect("should parse");
+
+        let mut interp = Interpreter::from_reader::<StdoutScratchPad, &[u8]>(
+            "a,b,
+,
+c
+"
+            .as_bytes(),
+        )
+        .expect("should read");
+
+        assert_eq!(Ok(true), interp.exec(&cmd));
+
+        assert_content!(interp.buffer, "a\nb\n\nc\n");
+        assert_eq!(interp.buffer.cursor(), 4);
+    }
+}
+
+mod flags {
+    use super::*;
+
+    const CONTENT: &str = r"
+Foo.bar
+foo
+FOO
+fooxbar
+";
+
+    #[test]
+    fn global_insensitive() {
+        let cmd = Command::from_str("g/^foo$/Id").expect("should parse");
+
+        let mut interp =
+            Interpreter::from_reader::<StdoutScratchPad, &[u8]>(CONTENT.trim().as_bytes())
+                .expect("should read");
+
+        assert_eq!(Ok(true), interp.exec(&cmd));
+
+        assert_content!(interp.buffer, "Foo.bar\nfooxbar\n");
+    }
+
+    #[test]
+    fn subst_literal() {
+        let cmd = Command::from_str("%s/o.b/0/L").expect("should parse");
+
+        let mut interp =
+            Interpreter::from_reader::<StdoutScratchPad, &[u8]>(CONTENT.trim().as_bytes())
+                .expect("should read");
+
+        assert_eq!(Ok(true), interp.exec(&cmd));
+
+        assert_content!(interp.buffer, "Fo0ar\nfoo\nFOO\nfooxbar\n");
+    }
+
+    #[test]
+    fn replays_last_re() {
+        let mut interp =
+            Interpreter::from_reader::<StdoutScratchPad, &[u8]>(CONTENT.trim().as_bytes())
+                .expect("should read");
+
+        let cmd = Command::from_str("2s/^foo$/x/i").expect("should parse");
+        assert_eq!(Ok(true), interp.exec(&cmd));
+
+        let cmd = Command::from_str("3s//y/").expect("should parse");
+        assert_eq!(Ok(true), interp.exec(&cmd));
+
+        assert_content!(interp.buffer, "Foo.bar\nx\ny\nfooxbar\n");
+    }
+}
+
 mod v {
     use super::*;
 
@@ -179,3 +336,1010 @@ barfoo
         assert_content!(interp.buffer, "foobar bar needle\nbarfoo\n");
     }
 }
+
+mod s {
+    use super::*;
+    use crate::ed::cmd::{Answer, InvocationError, SubstFlags};
+    use crate::interp::scratch::{ScratchPad, StoreScratchPad};
+    use std::collections::VecDeque;
+    use std::ops::Range;
+
+    #[derive(Default)]
+    struct AnswerPad(VecDeque<Answer>);
+
+    impl ScratchPad for AnswerPad {
+        fn print(&mut self, _: &str) {}
+
+        fn confirm(&mut self, _: &str, _: Range<usize>) -> Answer {
+            self.0.pop_front().unwrap_or(Answer::Quit)
+        }
+    }
+
+    const CONTENT: &str = r"
+foo foo
+bar
+foo
+";
+
+    fn confirming(answers: &[Answer]) -> Interpreter<AnswerPad> {
+        let mut interp = Interpreter::from_reader::<AnswerPad, &[u8]>(CONTENT.trim().as_bytes())
+            .expect("should read");
+        interp.scratch.0 = answers.iter().cloned().collect();
+
+        interp
+    }
+
+    #[test]
+    fn counter() {
+        let cmd = Command::from_str("%s/^/\\#{10,5}. /").expect("should parse");
+
+        let mut interp =
+            Interpreter::from_reader::<StdoutScratchPad, &[u8]>(CONTENT.trim().as_bytes())
+                .expect("should read");
+
+        assert_eq!(Ok(true), interp.exec(&cmd));
+
+        assert_content!(interp.buffer, "10. foo foo\n15. bar\n20. foo\n");
+    }
+
+    #[test]
+    fn counter_in_global() {
+        let cmd = Command::from_str("g/foo/s/^/\\#. /").expect("should parse");
+
+        let mut interp =
+            Interpreter::from_reader::<StdoutScratchPad, &[u8]>(CONTENT.trim().as_bytes())
+                .expect("should read");
+
+        assert_eq!(Ok(true), interp.exec(&cmd));
+        assert_content!(interp.buffer, "1. foo foo\nbar\n2. foo\n");
+
+        let cmd = Command::from_str("g/foo/s/\\./:\\#/").expect("should parse");
+        assert_eq!(Ok(true), interp.exec(&cmd));
+        assert_content!(interp.buffer, "1:1 foo foo\nbar\n2:2 foo\n");
+    }
+
+    #[test]
+    fn counter_overflow() {
+        let cmd = Command::from_str("%s/^/\\#{9223372036854775807}. /").expect("should parse");
+
+        let mut interp =
+            Interpreter::from_reader::<StdoutScratchPad, &[u8]>(CONTENT.trim().as_bytes())
+                .expect("should read");
+
+        assert_eq!(Err(InvocationError::CounterOverflow), interp.exec(&cmd));
+        assert_content!(interp.buffer, "9223372036854775807. foo foo\nbar\nfoo\n");
+
+        let cmd = Command::from_str("%s/foo/\\#{0,-9223372036854775807}/gc").expect("should parse");
+        let mut interp = confirming(&[Answer::All]);
+
+        assert_eq!(Err(InvocationError::CounterOverflow), interp.exec(&cmd));
+        assert_content!(interp.buffer, "0 -9223372036854775807\nbar\nfoo\n");
+    }
+
+    #[test]
+    fn line_number() {
+        let cmd = Command::from_str("%s/foo/&@\\=/g").expect("should parse");
+
+        let mut interp =
+            Interpreter::from_reader::<StdoutScratchPad, &[u8]>(CONTENT.trim().as_bytes())
+                .expect("should read");
+        interp.env.filename = Some("list.txt".to_string());
+
+        assert_eq!(Ok(true), interp.exec(&cmd));
+
+        let cmd = Command::from_str("2s/^/\\f: /").expect("should parse");
+        assert_eq!(Ok(true), interp.exec(&cmd));
+
+        assert_content!(interp.buffer, "foo@1 foo@1\nlist.txt: bar\nfoo@3\n");
+    }
+
+    #[test]
+    fn confirm_counter() {
+        let cmd = Command::from_str("%s/foo/\\#/gc").expect("should parse");
+        let mut interp = confirming(&[Answer::No, Answer::Yes, Answer::Yes]);
+
+        assert_eq!(Ok(true), interp.exec(&cmd));
+
+        assert_content!(interp.buffer, "foo 1\nbar\n2\n");
+    }
+
+    #[test]
+    fn confirm_each() {
+        let cmd = Command::from_str("%s/foo/x/gc").expect("should parse");
+        let mut interp = confirming(&[Answer::Yes, Answer::No, Answer::Yes]);
+
+        assert_eq!(Ok(true), interp.exec(&cmd));
+
+        assert_content!(interp.buffer, "x foo\nbar\nx\n");
+    }
+
+    #[test]
+    fn confirm_all() {
+        let cmd = Command::from_str("%s/foo/x/gc").expect("should parse");
+        let mut interp = confirming(&[Answer::No, Answer::All]);
+
+        assert_eq!(Ok(true), interp.exec(&cmd));
+
+        assert_content!(interp.buffer, "foo x\nbar\nx\n");
+    }
+
+    #[test]
+    fn confirm_quit() {
+        let cmd = Command::from_str("%s/foo/x/gc").expect("should parse");
+        let mut interp = confirming(&[Answer::Yes, Answer::Quit]);
+
+        assert_eq!(Ok(true), interp.exec(&cmd));
+
+        assert_content!(interp.buffer, "x foo\nbar\nfoo\n");
+    }
+
+    #[test]
+    fn confirm_none() {
+        let cmd = Command::from_str("%s/foo/x/gc").expect("should parse");
+        let mut interp = confirming(&[Answer::No, Answer::No, Answer::No]);
+
+        assert_eq!(Err(InvocationError::DidNotReplace), interp.exec(&cmd));
+
+        assert_content!(interp.buffer, "foo foo\nbar\nfoo\n");
+    }
+
+    #[test]
+    fn confirm_session_flags() {
+        let cmd = Command::from_str("%s/foo/x/gc").expect("should parse");
+        let mut interp = confirming(&[]);
+
+        let session = cmd.subst_session(&mut interp).expect("should start");
+        assert!(session.is_some());
+
+        let flags = SubstFlags {
+            print: false,
+            occurances: 0,
+            confirm: true,
+        };
+        assert_eq!(interp.env.last_flags, Some(flags));
+    }
+
+    #[test]
+    fn repeats_flags() {
+        let mut interp = confirming(&[]);
+
+        let cmd = Command::from_str("$s/o/0/g").expect("should parse");
+        assert_eq!(Ok(true), interp.exec(&cmd));
+
+        let cmd = Command::from_str("1s").expect("should parse");
+        assert_eq!(Ok(true), interp.exec(&cmd));
+
+        assert_content!(interp.buffer, "f00 f00\nbar\nf00\n");
+    }
+
+    #[test]
+    fn confirm_where_unable() {
+        let cmd = Command::from_str("g/foo/s/foo/x/c").expect("should parse");
+        let mut interp =
+            Interpreter::from_reader::<StoreScratchPad, &[u8]>(CONTENT.trim().as_bytes())
+                .expect("should read");
+
+        assert_eq!(Err(InvocationError::CannotConfirm), interp.exec(&cmd));
+
+        assert_content!(interp.buffer, "foo foo\nbar\nfoo\n");
+    }
+
+    #[test]
+    fn confirm_journaled() {
+        let cmd = Command::from_str("g/foo/s/foo/x/gc").expect("should parse");
+        let mut interp = confirming(&[Answer::Yes, Answer::No, Answer::Yes]);
+
+        assert_eq!(Ok(true), interp.exec_typed(&cmd));
+
+        assert_content!(interp.buffer, "x foo\nbar\nx\n");
+        assert_eq!(interp.env.journal.lines(), &["1c \"x foo\"", "3s/foo/x/g"]);
+    }
+}
+
+mod sort {
+    use super::*;
+
+    const CONTENT: &str = r"
+pear 10
+Apple 2
+banana 33
+apple 2
+";
+
+    #[test]
+    fn plain() {
+        let cmd = Command::from_str("sort").expect("should parse");
+
+        let mut interp =
+            Interpreter::from_reader::<StdoutScratchPad, &[u8]>(CONTENT.trim().as_bytes())
+                .expect("should read");
+
+        assert_eq!(Ok(true), interp.exec(&cmd));
+
+        assert_content!(interp.buffer, "Apple 2\napple 2\nbanana 33\npear 10\n");
+    }
+
+    #[test]
+    fn numeric_reverse() {
+        let cmd = Command::from_str("sort! n").expect("should parse");
+
+        let mut interp =
+            Interpreter::from_reader::<StdoutScratchPad, &[u8]>(CONTENT.trim().as_bytes())
+                .expect("should read");
+
+        assert_eq!(Ok(true), interp.exec(&cmd));
+
+        assert_content!(interp.buffer, "banana 33\npear 10\nApple 2\napple 2\n");
+    }
+
+    #[test]
+    fn unique_insensitive() {
+        let cmd = Command::from_str("sort iu").expect("should parse");
+
+        let mut interp =
+            Interpreter::from_reader::<StdoutScratchPad, &[u8]>(CONTENT.trim().as_bytes())
+                .expect("should read");
+
+        assert_eq!(Ok(true), interp.exec(&cmd));
+
+        assert_content!(interp.buffer, "Apple 2\nbanana 33\npear 10\n");
+    }
+
+    #[test]
+    fn key() {
+        let cmd = Command::from_str("sort /^(.)/").expect("should parse");
+
+        let mut interp =
+            Interpreter::from_reader::<StdoutScratchPad, &[u8]>(CONTENT.trim().as_bytes())
+                .expect("should read");
+
+        assert_eq!(Ok(true), interp.exec(&cmd));
+
+        assert_content!(interp.buffer, "Apple 2\napple 2\nbanana 33\npear 10\n");
+    }
+
+    #[test]
+    fn keeps_marks() {
+        let mut interp =
+            Interpreter::from_reader::<StdoutScratchPad, &[u8]>(CONTENT.trim().as_bytes())
+                .expect("should read");
+
+        let cmd = Command::from_str("1ka").expect("should parse");
+        assert_eq!(Ok(true), interp.exec(&cmd));
+
+        let cmd = Command::from_str("sort").expect("should parse");
+        assert_eq!(Ok(true), interp.exec(&cmd));
+
+        assert_eq!(interp.buffer.mark('a'), Some(4));
+    }
+
+    #[test]
+    fn uniq() {
+        let cmd = Command::from_str("uniq i").expect("should parse");
+
+        let mut interp =
+            Interpreter::from_reader::<StdoutScratchPad, &[u8]>("a\nA\nb\na\n".as_bytes())
+                .expect("should read");
+
+        assert_eq!(Ok(true), interp.exec(&cmd));
+
+        assert_content!(interp.buffer, "a\nb\na\n");
+    }
+
+    #[test]
+    fn uniq_in_global() {
+        let cmd = Command::from_str("g/x/.,+1uniq\\\ns/$/!/").expect("should parse");
+
+        let mut interp =
+            Interpreter::from_reader::<StdoutScratchPad, &[u8]>("x\nx\ny\n".as_bytes())
+                .expect("should read");
+        interp.buffer.make_mark('k', 2);
+        interp.buffer.make_mark('y', 3);
+
+        assert_eq!(Ok(true), interp.exec(&cmd));
+
+        assert_content!(interp.buffer, "x!\ny\n");
+        assert_eq!(interp.buffer.mark('k'), None);
+        assert_eq!(interp.buffer.mark('y'), Some(2));
+    }
+
+    #[test]
+    fn tac() {
+        let cmd = Command::from_str("2,3tac").expect("should parse");
+
+        let mut interp =
+            Interpreter::from_reader::<StdoutScratchPad, &[u8]>(CONTENT.trim().as_bytes())
+                .expect("should read");
+
+        assert_eq!(Ok(true), interp.exec(&cmd));
+
+        assert_content!(interp.buffer, "pear 10\nbanana 33\nApple 2\napple 2\n");
+    }
+
+    #[test]
+    fn in_global() {
+        let cmd = Command::from_str("g/^b/.,+1sort").expect("should parse");
+
+        let mut interp =
+            Interpreter::from_reader::<StdoutScratchPad, &[u8]>("b\na\nb\na\n".as_bytes())
+                .expect("should read");
+
+        assert_eq!(Ok(true), interp.exec(&cmd));
+
+        assert_content!(interp.buffer, "a\nb\na\nb\n");
+    }
+}
+
+mod shift {
+    use super::*;
+    use crate::interp::Indent;
+
+    const CONTENT: &str = "fn main() {\nlet x = 1;\n\n\tx\n}\n";
+
+    #[test]
+    fn indent() {
+        let cmd = Command::from_str("2,4>").expect("should parse");
+
+        let mut interp = Interpreter::from_reader::<StdoutScratchPad, &[u8]>(CONTENT.as_bytes())
+            .expect("should read");
+
+        assert_eq!(Ok(true), interp.exec(&cmd));
+
+        assert_content!(interp.buffer, "fn main() {\n    let x = 1;\n\n    \tx\n}\n");
+    }
+
+    #[test]
+    fn indent_tabs() {
+        let cmd = Command::from_str("2,4>2").expect("should parse");
+
+        let mut interp = Interpreter::from_reader::<StdoutScratchPad, &[u8]>(CONTENT.as_bytes())
+            .expect("should read");
+        interp.env.indent = Indent::Tabs;
+
+        assert_eq!(Ok(true), interp.exec(&cmd));
+
+        assert_content!(interp.buffer, "fn main() {\n\t\tlet x = 1;\n\n\t\t\tx\n}\n");
+    }
+
+    #[test]
+    fn dedent() {
+        let cmd = Command::from_str("%<").expect("should parse");
+
+        let mut interp = Interpreter::from_reader::<StdoutScratchPad, &[u8]>(
+            "      a\n  b\n\tc\nd\n".as_bytes(),
+        )
+        .expect("should read");
+
+        assert_eq!(Ok(true), interp.exec(&cmd));
+
+        assert_content!(interp.buffer, "  a\nb\nc\nd\n");
+    }
+}
+
+mod reflow {
+    use super::*;
+
+    const CONTENT: &str = r"
+one two three four five
+six seven
+
+eight nine ten
+";
+
+    #[test]
+    fn paragraphs() {
+        let cmd = Command::from_str("%fmt 10").expect("should parse");
+
+        let mut interp =
+            Interpreter::from_reader::<StdoutScratchPad, &[u8]>(CONTENT.trim().as_bytes())
+                .expect("should read");
+
+        assert_eq!(Ok(true), interp.exec(&cmd));
+
+        assert_content!(
+            interp.buffer,
+            "one two\nthree four\nfive six\nseven\n\neight nine\nten\n"
+        );
+    }
+
+    #[test]
+    fn default_width() {
+        let cmd = Command::from_str("1,2fmt").expect("should parse");
+
+        let mut interp =
+            Interpreter::from_reader::<StdoutScratchPad, &[u8]>(CONTENT.trim().as_bytes())
+                .expect("should read");
+        interp.env.text_width = 14;
+
+        assert_eq!(Ok(true), interp.exec(&cmd));
+
+        assert_content!(
+            interp.buffer,
+            "one two three\nfour five six\nseven\n\neight nine ten\n"
+        );
+    }
+
+    #[test]
+    fn prefix() {
+        let cmd = Command::from_str("%fmt 16").expect("should parse");
+
+        let mut interp = Interpreter::from_reader::<StdoutScratchPad, &[u8]>(
+            "    // alpha beta gamma\n    // delta\n    //\n    // epsilon\n".as_bytes(),
+        )
+        .expect("should read");
+
+        assert_eq!(Ok(true), interp.exec(&cmd));
+
+        assert_content!(
+            interp.buffer,
+            "    // alpha\n    // beta\n    // gamma\n    // delta\n    //\n    // epsilon\n"
+        );
+    }
+
+    #[test]
+    fn keeps_marks() {
+        let mut interp =
+            Interpreter::from_reader::<StdoutScratchPad, &[u8]>(CONTENT.trim().as_bytes())
+                .expect("should read");
+
+        let cmd = Command::from_str("4ka").expect("should parse");
+        assert_eq!(Ok(true), interp.exec(&cmd));
+
+        let cmd = Command::from_str("%fmt 10").expect("should parse");
+        assert_eq!(Ok(true), interp.exec(&cmd));
+
+        assert_eq!(interp.buffer.mark('a'), Some(6));
+    }
+}
+
+mod align {
+    use super::*;
+    use crate::ed::cmd::InvocationError;
+
+    #[test]
+    fn first() {
+        let cmd = Command::from_str("%align/=/").expect("should parse");
+
+        let mut interp = Interpreter::from_reader::<StdoutScratchPad, &[u8]>(
+            "  a = 1\n  long_name = 2 = 3\nno match\n  ünï = 4\n".as_bytes(),
+        )
+        .expect("should read");
+
+        assert_eq!(Ok(true), interp.exec(&cmd));
+
+        assert_content!(
+            interp.buffer,
+            "  a         = 1\n  long_name = 2 = 3\nno match\n  ünï       = 4\n"
+        );
+    }
+
+    #[test]
+    fn table() {
+        let cmd = Command::from_str("%align/|/gL").expect("should parse");
+
+        let mut interp = Interpreter::from_reader::<StdoutScratchPad, &[u8]>(
+            "| name | qty |\n|---|---|\n| 漢字 | 1 |\n".as_bytes(),
+        )
+        .expect("should read");
+
+        assert_eq!(Ok(true), interp.exec(&cmd));
+
+        assert_content!(
+            interp.buffer,
+            "| name | qty |\n| ---  | --- |\n| 漢字 | 1   |\n"
+        );
+    }
+
+    #[test]
+    fn missing_pattern() {
+        let cmd = Command::from_str("align//").expect("should parse");
+
+        let mut interp = Interpreter::from_reader::<StdoutScratchPad, &[u8]>("a = 1\n".as_bytes())
+            .expect("should read");
+
+        assert_eq!(Err(InvocationError::MissingPattern), interp.exec(&cmd));
+    }
+}
+
+mod translit {
+    use super::*;
+    use crate::ed::cmd::InvocationError;
+
+    #[test]
+    fn range() {
+        let cmd = Command::from_str("%tr/a-z/A-Z/").expect("should parse");
+
+        let mut interp =
+            Interpreter::from_reader::<StdoutScratchPad, &[u8]>("hello\nWorld 42\n".as_bytes())
+                .expect("should read");
+
+        assert_eq!(Ok(true), interp.exec(&cmd));
+
+        assert_content!(interp.buffer, "HELLO\nWORLD 42\n");
+    }
+
+    #[test]
+    fn global() {
+        let cmd = Command::from_str("g/^#/tr/ab/ba/").expect("should parse");
+
+        let mut interp =
+            Interpreter::from_reader::<StdoutScratchPad, &[u8]>("# abc\nabc\n# bad\n".as_bytes())
+                .expect("should read");
+
+        assert_eq!(Ok(true), interp.exec(&cmd));
+
+        assert_content!(interp.buffer, "# bac\nabc\n# abd\n");
+    }
+
+    #[test]
+    fn unchanged() {
+        let cmd = Command::from_str("tr/x/y/").expect("should parse");
+
+        let mut interp = Interpreter::from_reader::<StdoutScratchPad, &[u8]>("abc\n".as_bytes())
+            .expect("should read");
+
+        assert_eq!(Err(InvocationError::DidNotReplace), interp.exec(&cmd));
+    }
+}
+
+mod increment {
+    use super::*;
+    use crate::ed::cmd::InvocationError;
+
+    fn run(content: &str, cmd: &str) -> Interpreter {
+        let cmd = Command::from_str(cmd).expect("should parse");
+
+        let mut interp = Interpreter::from_reader::<StdoutScratchPad, &[u8]>(content.as_bytes())
+            .expect("should read");
+
+        assert_eq!(Ok(true), interp.exec(&cmd));
+
+        interp
+    }
+
+    #[test]
+    fn decimal() {
+        let mut interp = run("a 9\nb -3\nc 007\nd 099\nnone\n", "%inc 4");
+
+        assert_content!(interp.buffer, "a 13\nb 1\nc 011\nd 103\nnone\n");
+    }
+
+    #[test]
+    fn negative() {
+        let mut interp = run("x 2\ny -004\n", "%dec 5");
+
+        assert_content!(interp.buffer, "x -3\ny -009\n");
+    }
+
+    #[test]
+    fn radix() {
+        let mut interp = run("0x0f\n0XFF\n0b0111\n", "%inc");
+
+        assert_content!(interp.buffer, "0x10\n0X100\n0b1000\n");
+    }
+
+    #[test]
+    fn sequential() {
+        let mut interp = run("0. a\n0. b\n\n0. c\n", "%inc g");
+
+        assert_content!(interp.buffer, "1. a\n2. b\n\n3. c\n");
+    }
+
+    #[test]
+    fn nth() {
+        let mut interp = Interpreter::from_reader::<StdoutScratchPad, &[u8]>(
+            "name = \"er\"\nversion = \"0.1.9\"\n".as_bytes(),
+        )
+        .expect("should read");
+
+        for cmd in Command::from_content("/^version/inc #3\n").expect("should parse") {
+            assert_eq!(Ok(true), interp.exec(&cmd));
+        }
+
+        assert_content!(interp.buffer, "name = \"er\"\nversion = \"0.1.10\"\n");
+    }
+
+    #[test]
+    fn out_of_range() {
+        for cmd in &["%inc", "3dec", "1inc 9223372036854775807"] {
+            let cmd = Command::from_str(cmd).expect("should parse");
+            let mut interp = Interpreter::from_reader::<StdoutScratchPad, &[u8]>(
+                "1\n99999999999999999999\n0x0\n".as_bytes(),
+            )
+            .expect("should read");
+
+            assert_eq!(Err(InvocationError::NumberOverflow), interp.exec(&cmd));
+            assert_content!(interp.buffer, "1\n99999999999999999999\n0x0\n");
+        }
+
+        let mut interp = run("-9223372036854775807\n0b1\n", "%dec");
+        assert_content!(interp.buffer, "-9223372036854775808\n0b0\n");
+    }
+}
+
+mod branch {
+    use super::*;
+    use crate::ed::cmd::InvocationError;
+
+    fn script(content: &str, script: &str) -> (Interpreter, Result<bool, InvocationError>) {
+        let cmds = Command::from_content(script).expect("should parse");
+
+        let mut interp = Interpreter::from_reader::<StdoutScratchPad, &[u8]>(content.as_bytes())
+            .expect("should read");
+        interp.env.loop_limit = 100;
+
+        let res = interp.exec_script(&cmds);
+
+        (interp, res)
+    }
+
+    #[test]
+    fn replaced() {
+        let (mut interp, res) = script("a    b  c\n", ":again\ns/  / /\nbt again\n");
+
+        assert_eq!(res, Ok(true));
+        assert_content!(interp.buffer, "a b c\n");
+    }
+
+    #[test]
+    fn untested_substitution() {
+        let (mut interp, res) = script("a  b\n", "s/x/y/\n:again\ns/  / /\nbt again\n");
+
+        assert_eq!(res, Err(InvocationError::DidNotReplace));
+        assert_content!(interp.buffer, "a  b\n");
+    }
+
+    #[test]
+    fn replaced_in_global() {
+        let (mut interp, res) = script("aaaa\nb\naa\n", ":again\ng/aa/s/aa/a/\nbt again\n");
+
+        assert_eq!(res, Ok(true));
+        assert_content!(interp.buffer, "a\nb\na\n");
+    }
+
+    #[test]
+    fn address() {
+        let skip = "/DONE/b\n%s/todo/DONE/\n";
+
+        let (mut interp, res) = script("todo\n", skip);
+        assert_eq!(res, Ok(true));
+        assert_content!(interp.buffer, "DONE\n");
+
+        let (mut interp, res) = script("todo\nDONE\n", skip);
+        assert_eq!(res, Ok(true));
+        assert_content!(interp.buffer, "todo\nDONE\n");
+    }
+
+    #[test]
+    fn loop_limit() {
+        let (_, res) = script("a\n", ":forever\nb forever\n");
+
+        assert_eq!(res, Err(InvocationError::LoopLimit));
+    }
+
+    #[test]
+    fn unknown_label() {
+        assert!(Command::from_content("p\nb nowhere\n").is_err());
+    }
+
+    #[test]
+    fn interactive() {
+        let cmd = Command::from_str("b").expect("should parse");
+
+        let mut interp = Interpreter::from_reader::<StdoutScratchPad, &[u8]>("a\n".as_bytes())
+            .expect("should read");
+
+        assert_eq!(Err(InvocationError::BranchOutsideScript), interp.exec(&cmd));
+    }
+}
+
+mod source {
+    use super::*;
+    use crate::ed::cmd::InvocationError;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    fn script(content: &str) -> NamedTempFile {
+        let mut file = NamedTempFile::new().expect("should create");
+        file.write_all(content.as_bytes()).expect("should write");
+
+        file
+    }
+
+    fn interp() -> Interpreter {
+        Interpreter::from_reader::<StdoutScratchPad, &[u8]>("one\ntwo\n".as_bytes())
+            .expect("should read")
+    }
+
+    fn source(file: &NamedTempFile) -> Command {
+        Command::from_str(&format!("so {}", file.path().display())).expect("should parse")
+    }
+
+    #[test]
+    fn runs() {
+        let file = script("# comment\n%s/o/0/g\n$a\nthree\n.\n");
+        let mut interp = interp();
+
+        assert_eq!(Ok(true), interp.exec(&source(&file)));
+
+        assert_content!(interp.buffer, "0ne\ntw0\nthree\n");
+    }
+
+    #[test]
+    fn failing_line() {
+        let file = script("1d\n\n/nowhere/p\n");
+        let mut interp = interp();
+
+        assert_eq!(
+            Err(InvocationError::SourceLine(
+                3,
+                Box::new(InvocationError::AddressNonResolvable)
+            )),
+            interp.exec(&source(&file))
+        );
+    }
+
+    #[test]
+    fn parse_failure() {
+        let file = script("1d\nbogus\n");
+        let mut interp = interp();
+
+        assert_eq!(
+            Err(InvocationError::SourceParse(2)),
+            interp.exec(&source(&file))
+        );
+    }
+
+    #[test]
+    fn recursive() {
+        let mut file = script("1d\n");
+        let path = file.path().display().to_string();
+        writeln!(file, "so {}", path).expect("should write");
+        let mut interp = interp();
+
+        assert_eq!(
+            Err(InvocationError::SourceLine(
+                2,
+                Box::new(InvocationError::RecursiveSource)
+            )),
+            interp.exec(&source(&file))
+        );
+    }
+}
+
+mod journal {
+    use super::*;
+    use std::fs::read_to_string;
+    use tempfile::NamedTempFile;
+
+    fn interp(content: &str) -> Interpreter {
+        Interpreter::from_reader::<StdoutScratchPad, &[u8]>(content.as_bytes())
+            .expect("should read")
+    }
+
+    fn typed(interp: &mut Interpreter, typed: &str, text: Option<&[&str]>) {
+        let mut cmd = Command::from_str(typed).expect("should parse");
+        if let Some(text) = text {
+            cmd.inject(text.iter().map(|line| line.to_string()).collect());
+        }
+
+        let _ = interp.exec_typed(&cmd);
+    }
+
+    #[test]
+    fn replays() {
+        let file = NamedTempFile::new().expect("should create");
+        let mut session = interp("one\ntwo\n");
+
+        typed(&mut session, "%s/o/0/g", None);
+        typed(&mut session, "/nowhere/d", None);
+        typed(&mut session, "$a", Some(&["three"]));
+        typed(&mut session, "w /dev/null", None);
+        typed(&mut session, "g/0/s/$/!/\\\np", None);
+        typed(
+            &mut session,
+            &format!("journal {}", file.path().display()),
+            None,
+        );
+
+        let script = read_to_string(file.path()).expect("should read");
+        assert_eq!("1,$s/o/0/g\n$a \"three\"\ng/0/s/$/!/\\\np\n", script);
+
+        let cmds = Command::from_content(&script).expect("should parse");
+        let mut replay = interp("one\ntwo\n");
+        for cmd in &cmds {
+            replay.exec(cmd).expect("should run");
+        }
+
+        assert_content!(replay.buffer, "0ne!\ntw0!\nthree\n");
+        assert_content!(session.buffer, "0ne!\ntw0!\nthree\n");
+    }
+
+    #[test]
+    fn replays_confirmed() {
+        use crate::ed::cmd::Answer::*;
+
+        let mut session = interp("foo foo\nfoo\nbar\nfoo bar\n");
+        let cmd = Command::from_str("%s/foo/x/gc").expect("should parse");
+        let mut confirm = cmd
+            .subst_session(&mut session)
+            .expect("should run")
+            .expect("should ask");
+
+        for answer in [Yes, No, Yes, Yes] {
+            confirm.answer(answer, &mut session.buffer, &mut session.scratch);
+        }
+
+        assert!(confirm.is_done());
+        session.env.journal.keep_all(confirm.made());
+
+        let script = session.env.journal.lines().join("\n");
+        assert_eq!("1c \"x foo\"\n2s/foo/x/g\n4s/foo/x/g", script);
+
+        let cmds = Command::from_content(&script).expect("should parse");
+        let mut replay = interp("foo foo\nfoo\nbar\nfoo bar\n");
+        for cmd in &cmds {
+            replay.exec(cmd).expect("should run");
+        }
+
+        assert_content!(replay.buffer, "x foo\nx\nbar\nx bar\n");
+        assert_content!(session.buffer, "x foo\nx\nbar\nx bar\n");
+    }
+
+    #[test]
+    fn comment_ending_in_backslash() {
+        let cmds = Command::from_content("# files live in C:\\\n%s/o/0/g\n").expect("should parse");
+        let mut interp = interp("one\ntwo\n");
+        for cmd in &cmds {
+            interp.exec(cmd).expect("should run");
+        }
+
+        assert_content!(interp.buffer, "0ne\ntw0\n");
+    }
+}
+
+mod set {
+    use super::*;
+    use crate::interp::scratch::BufferedScratchPad;
+    use crate::{ed::cmd::InvocationError, interp::Indent};
+
+    #[test]
+    fn settings() {
+        let mut interp =
+            Interpreter::from_reader::<StdoutScratchPad, &[u8]>("a\n".as_bytes()).unwrap();
+
+        let cmds = Command::from_content("set indent=tabs\nset scroll=5\nset textwidth=60")
+            .expect("should parse");
+        assert_eq!(Ok(true), interp.exec_script(&cmds));
+
+        assert_eq!(interp.env.indent, Indent::Tabs);
+        assert_eq!(interp.env.scroll, Some(5));
+        assert_eq!(interp.env.text_width, 60);
+    }
+
+    #[test]
+    fn invalid() {
+        let mut interp =
+            Interpreter::from_reader::<StdoutScratchPad, &[u8]>("a\n".as_bytes()).unwrap();
+
+        let cmd = Command::from_str("set looplimit=-1").expect("should parse");
+        assert_eq!(Err(InvocationError::InvalidValue), interp.exec(&cmd));
+        assert_eq!(interp.env.loop_limit, 10_000);
+    }
+
+    #[test]
+    fn query() {
+        let mut interp = Interpreter::from_text::<BufferedScratchPad>("a\n");
+
+        assert_eq!(
+            Ok(true),
+            interp.run("set shiftwidth=tabs\nset prompt=er>\nset indent?")
+        );
+        assert_eq!(interp.scratch_mut().take(), "indent=tabs\n");
+
+        assert_eq!(Ok(true), interp.run("set"));
+        assert_eq!(
+            interp.scratch_mut().take(),
+            "scroll=22\nindent=tabs\ntextwidth=72\nlooplimit=10000\nwritehook=\n\
+             wrapsearch=off\nignorecase=off\nprompt=er>\n"
+        );
+    }
+
+    #[test]
+    fn wrap_search() {
+        let mut interp = Interpreter::from_text::<BufferedScratchPad>("one\ntwo\nthree\n");
+
+        assert_eq!(
+            Err(InvocationError::SourceLine(
+                1,
+                Box::new(InvocationError::AddressNonResolvable)
+            )),
+            interp.run("/one/p")
+        );
+
+        assert_eq!(Ok(true), interp.run("set wrapsearch=on\n/one/p\n?three?p"));
+        assert_eq!(interp.scratch_mut().take(), "one\nthree\n");
+    }
+
+    #[test]
+    fn prompt() {
+        let mut interp = Interpreter::from_text::<BufferedScratchPad>("a\n");
+
+        let cmd = Command::from_str("set prompt=> ").expect("should parse");
+        assert_eq!(Ok(true), interp.exec(&cmd));
+        assert_eq!(interp.env.prompt, "> ");
+
+        assert_eq!(Ok(true), interp.run("set prompt=\"er> \"\nset scroll= 5 "));
+        assert_eq!(interp.env.prompt, "er> ");
+        assert_eq!(interp.env.scroll, Some(5));
+    }
+
+    #[test]
+    fn ignore_case() {
+        let mut interp = Interpreter::from_text::<BufferedScratchPad>("One\ntwo\nONE\n");
+
+        assert_eq!(Ok(true), interp.run("set ignorecase=on\ng/one/s/o/0/g"));
+        assert_eq!(interp.buffer().lines(), &["0ne", "two", "0NE"]);
+
+        let cmd = Command::from_str("1,$s/T/t/").expect("should parse");
+        assert_eq!(Ok(true), interp.exec_typed(&cmd));
+        assert_eq!(interp.buffer().lines(), &["0ne", "two", "0NE"]);
+
+        let cmd = Command::from_str("?TWO?s/O/0/").expect("should parse");
+        assert_eq!(Ok(true), interp.exec(&cmd));
+        assert_eq!(interp.buffer().lines(), &["0ne", "tw0", "0NE"]);
+
+        let cmd = Command::from_str("%s/W/x/c").expect("should parse");
+        let session = cmd.subst_session(&mut interp).expect("should match");
+        assert!(session.is_some());
+    }
+}
+
+mod run {
+    use super::*;
+    use crate::ed::cmd::InvocationError;
+    use crate::interp::scratch::BufferedScratchPad;
+
+    #[test]
+    fn scripts() {
+        let mut interp = Interpreter::from_text::<BufferedScratchPad>("one\ntwo\nthree\n");
+
+        let script = "# number the lines\n%s/^/\\=: /\n$a\nfour\n.\ng/o/p";
+        assert_eq!(Ok(true), interp.run(script));
+
+        assert_eq!(
+            interp.buffer().lines(),
+            &["1: one", "2: two", "3: three", "four"]
+        );
+        assert_eq!(interp.scratch_mut().take(), "1: one\n2: two\nfour\n");
+    }
+
+    #[test]
+    fn errors() {
+        let mut interp = Interpreter::from_text::<BufferedScratchPad>("one\n");
+
+        assert_eq!(
+            Err(InvocationError::SourceParse(2)),
+            interp.run("p\ns/o/0/gx\np")
+        );
+
+        assert_eq!(
+            Err(InvocationError::SourceLine(
+                3,
+                Box::new(InvocationError::DidNotReplace)
+            )),
+            interp.run("p\n\ns/x/y/\np")
+        );
+
+        assert_eq!(interp.scratch_mut().take(), "one\n");
+    }
+
+    #[test]
+    fn custom_scratch() {
+        let interp = Interpreter::from_text::<StdoutScratchPad>("one\n");
+        let mut interp = interp.with_scratch(BufferedScratchPad::default());
+
+        assert_eq!(Ok(true), interp.run(",p"));
+        assert_eq!(interp.scratch_mut().take(), "one\n");
+    }
+}
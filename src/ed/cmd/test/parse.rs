@@ -7,6 +7,7 @@ fn bogus() {
 
 mod print {
     use super::*;
+    use crate::ed::re::ReFlags;
 
     #[test]
     fn default() {
@@ -26,6 +27,36 @@ mod print {
             })
         );
     }
+
+    #[test]
+    fn regex_flags() {
+        let insensitive = ReFlags {
+            insensitive: true,
+            literal: false,
+        };
+
+        assert_parse!(
+            "/todo/ip",
+            Command::Print(Address::Line(Offset::Nil(Point::Ref(
+                Re::with_flags("todo", insensitive).unwrap()
+            ))))
+        );
+
+        assert_parse!(
+            "?todo?i,$p",
+            Command::Print(Address::Range {
+                start: Offset::Nil(Point::Reb(Re::with_flags("todo", insensitive).unwrap())),
+                end: Offset::Nil(Point::Last),
+            })
+        );
+    }
+
+    #[test]
+    fn invalid_regex() {
+        refute_parse!("/(/p");
+        refute_parse!("?[?p");
+        refute_parse!("/a/,/(/p");
+    }
 }
 
 mod delete {
@@ -70,6 +101,22 @@ mod insert {
         );
     }
 
+    #[test]
+    fn after_regex() {
+        assert_parse!(
+            "/todo/i",
+            Command::Insert(Offset::Nil(Point::Ref(re!("todo"))), None)
+        );
+
+        assert_parse!(
+            "/todo/i 'x'",
+            Command::Insert(
+                Offset::Nil(Point::Ref(re!("todo"))),
+                Some(vec!["x".to_string()])
+            )
+        );
+    }
+
     #[test]
     fn escaped_quote() {
         assert_parse!(
@@ -211,6 +258,68 @@ mod join {
     }
 }
 
+mod join_with {
+    use super::*;
+
+    #[test]
+    fn separator() {
+        assert_parse!(
+            "1,3j ', '",
+            Command::JoinWith(
+                Address::Range {
+                    start: Offset::Nil(Point::Abs(1)),
+                    end: Offset::Nil(Point::Abs(3)),
+                },
+                ", ".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn empty() {
+        assert_parse!(
+            "j ''",
+            Command::JoinWith(
+                Address::Range {
+                    start: Offset::Nil(Point::Current),
+                    end: Offset::Relf(Point::Current, 1),
+                },
+                String::new()
+            )
+        );
+    }
+
+    #[test]
+    fn multiline() {
+        refute_parse!("j 'a\\nb'");
+    }
+}
+
+mod split {
+    use super::*;
+
+    #[test]
+    fn default() {
+        assert_parse!(
+            "split/, */",
+            Command::Split(Address::CURRENT, Some(re!(", *")), false)
+        );
+    }
+
+    #[test]
+    fn keep() {
+        assert_parse!(
+            "%split/;/k",
+            Command::Split(Address::FULL, Some(re!(";")), true)
+        );
+    }
+
+    #[test]
+    fn prev_regex() {
+        assert_parse!("split//", Command::Split(Address::CURRENT, None, false));
+    }
+}
+
 mod r#move {
     use super::*;
 
@@ -244,7 +353,7 @@ mod transfer {
 mod subst {
     use super::*;
     use crate::ed::cmd::SubstFlags;
-    use crate::ed::re::Pat;
+    use crate::ed::re::{Pat, ReFlags};
 
     #[test]
     fn default() {
@@ -256,7 +365,8 @@ mod subst {
                 Some(Pat::from_str("-").unwrap()),
                 Some(SubstFlags {
                     occurances: 1,
-                    print: false
+                    print: false,
+                    confirm: false
                 })
             )
         );
@@ -272,7 +382,8 @@ mod subst {
                 Some(Pat::from_str("").unwrap()),
                 Some(SubstFlags {
                     occurances: 1,
-                    print: false
+                    print: false,
+                    confirm: false
                 })
             )
         );
@@ -293,7 +404,8 @@ mod subst {
                 Some(Pat::from_str("").unwrap()),
                 Some(SubstFlags {
                     occurances: 10,
-                    print: false
+                    print: false,
+                    confirm: false
                 })
             )
         );
@@ -306,7 +418,8 @@ mod subst {
                 Some(Pat::from_str("").unwrap()),
                 Some(SubstFlags {
                     occurances: 10,
-                    print: true
+                    print: true,
+                    confirm: false
                 })
             )
         );
@@ -319,7 +432,8 @@ mod subst {
                 Some(Pat::from_str("").unwrap()),
                 Some(SubstFlags {
                     occurances: 10,
-                    print: true
+                    print: true,
+                    confirm: false
                 })
             )
         );
@@ -332,7 +446,8 @@ mod subst {
                 Some(Pat::from_str("").unwrap()),
                 Some(SubstFlags {
                     occurances: 0,
-                    print: true
+                    print: true,
+                    confirm: false
                 })
             )
         );
@@ -344,7 +459,88 @@ mod subst {
                 Some(Pat::from_str("").unwrap()),
                 Some(SubstFlags {
                     occurances: 1,
-                    print: true
+                    print: true,
+                    confirm: false
+                })
+            )
+        );
+    }
+
+    #[test]
+    fn confirm() {
+        assert_parse!(
+            "s/ //gc",
+            Command::Subst(
+                Address::Line(Offset::Nil(Point::Current)),
+                Some(re!(" ")),
+                Some(Pat::from_str("").unwrap()),
+                Some(SubstFlags {
+                    occurances: 0,
+                    print: false,
+                    confirm: true
+                })
+            )
+        );
+
+        assert_parse!(
+            "s/ //cp",
+            Command::Subst(
+                Address::Line(Offset::Nil(Point::Current)),
+                Some(re!(" ")),
+                Some(Pat::from_str("").unwrap()),
+                Some(SubstFlags {
+                    occurances: 1,
+                    print: true,
+                    confirm: true
+                })
+            )
+        );
+    }
+
+    #[test]
+    fn regex_flags() {
+        assert_parse!(
+            "s/foo/bar/gi",
+            Command::Subst(
+                Address::Line(Offset::Nil(Point::Current)),
+                Some(
+                    Re::with_flags(
+                        "foo",
+                        ReFlags {
+                            insensitive: true,
+                            literal: false
+                        }
+                    )
+                    .unwrap()
+                ),
+                Some(Pat::from_str("bar").unwrap()),
+                Some(SubstFlags {
+                    occurances: 0,
+                    print: false,
+                    confirm: false
+                })
+            )
+        );
+
+        assert_parse!(
+            "s/a.b/c/LI",
+            Command::Subst(
+                Address::Line(Offset::Nil(Point::Current)),
+                Some(
+                    Re::with_flags(
+                        "a.b",
+                        ReFlags {
+                            insensitive: true,
+                            literal: true
+                        }
+                    )
+                    .unwrap()
+                ),
+                Some(Pat::from_str("c").unwrap()),
+                Some(SubstFlags {
+                    occurances: 1,
+                    print: false,
+                    confirm: false
                 })
             )
         );
@@ -360,7 +556,8 @@ mod subst {
                 Some(Pat::Replay),
                 Some(SubstFlags {
                     occurances: 1,
-                    print: false
+                    print: false,
+                    confirm: false
                 })
             )
         );
@@ -376,7 +573,8 @@ mod subst {
                 Some(Pat::from_str("foobar").unwrap()),
                 Some(SubstFlags {
                     occurances: 1,
-                    print: false
+                    print: false,
+                    confirm: false
                 })
             )
         );
@@ -512,3 +710,383 @@ mod quit {
         assert_parse!("q", Command::Quit);
     }
 }
+
+mod sort {
+    use super::*;
+    use crate::ed::cmd::SortFlags;
+
+    #[test]
+    fn default() {
+        assert_parse!("sort", Command::Sort(Address::FULL, SortFlags::default()));
+    }
+
+    #[test]
+    fn flags() {
+        assert_parse!(
+            "'a,'bsort! nu /: (\\d+)/",
+            Command::Sort(
+                Address::Range {
+                    start: Offset::Nil(Point::Mark('a')),
+                    end: Offset::Nil(Point::Mark('b')),
+                },
+                SortFlags {
+                    reverse: true,
+                    numeric: true,
+                    insensitive: false,
+                    unique: true,
+                    key: Some(re!(": (\\d+)")),
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn uniq() {
+        assert_parse!("uniq", Command::Uniq(Address::FULL, false));
+        assert_parse!(
+            "1,5uniq i",
+            Command::Uniq(
+                Address::Range {
+                    start: Offset::Nil(Point::Abs(1)),
+                    end: Offset::Nil(Point::Abs(5)),
+                },
+                true
+            )
+        );
+    }
+
+    #[test]
+    fn tac() {
+        assert_parse!("tac", Command::Tac(Address::FULL));
+    }
+}
+
+mod shift {
+    use super::*;
+
+    #[test]
+    fn current() {
+        assert_parse!(">", Command::Indent(Address::CURRENT, 1));
+        assert_parse!("<2", Command::Dedent(Address::CURRENT, 2));
+    }
+
+    #[test]
+    fn address() {
+        assert_parse!(
+            "1,3>",
+            Command::Indent(
+                Address::Range {
+                    start: Offset::Nil(Point::Abs(1)),
+                    end: Offset::Nil(Point::Abs(3)),
+                },
+                1
+            )
+        );
+
+        assert_parse!(
+            ".<3",
+            Command::Dedent(Address::Line(Offset::Nil(Point::Current)), 3)
+        );
+    }
+
+    #[test]
+    fn in_global() {
+        assert_parse!(
+            "g/x/>",
+            Command::Global(
+                Address::FULL,
+                Some(re!("x")),
+                vec![Command::Indent(Address::CURRENT, 1)]
+            )
+        );
+    }
+
+    #[test]
+    fn buffers() {
+        assert_parse!("next", Command::NextBuffer);
+        assert_parse!("prev", Command::PrevBuffer);
+        refute_parse!("1next");
+    }
+
+    #[test]
+    fn too_many_levels() {
+        refute_parse!(">99999999999999999999");
+        refute_parse!("1,2<99999999999999999999");
+    }
+}
+
+mod reflow {
+    use super::*;
+
+    #[test]
+    fn default() {
+        assert_parse!("fmt", Command::Reflow(Address::CURRENT, None));
+    }
+
+    #[test]
+    fn width() {
+        assert_parse!("%fmt 20", Command::Reflow(Address::FULL, Some(20)));
+    }
+
+    #[test]
+    fn width_too_large() {
+        refute_parse!("fmt 99999999999999999999");
+    }
+}
+
+mod align {
+    use super::*;
+    use crate::ed::re::ReFlags;
+
+    #[test]
+    fn default() {
+        assert_parse!(
+            "align/=/",
+            Command::Align(Address::FULL, Some(re!("=")), false)
+        );
+    }
+
+    #[test]
+    fn every() {
+        assert_parse!(
+            "1,3align/|/gL",
+            Command::Align(
+                Address::Range {
+                    start: Offset::Nil(Point::Abs(1)),
+                    end: Offset::Nil(Point::Abs(3)),
+                },
+                Some(
+                    Re::with_flags(
+                        "|",
+                        ReFlags {
+                            insensitive: false,
+                            literal: true
+                        }
+                    )
+                    .unwrap()
+                ),
+                true
+            )
+        );
+    }
+}
+
+mod translit {
+    use super::*;
+
+    #[test]
+    fn default() {
+        assert_parse!(
+            "tr/ab/xy/",
+            Command::Translit(Address::CURRENT, vec![('a', 'x'), ('b', 'y')])
+        );
+    }
+
+    #[test]
+    fn ranges() {
+        assert_parse!(
+            "%tr/a-c\\-/A-C_/",
+            Command::Translit(
+                Address::FULL,
+                vec![('a', 'A'), ('b', 'B'), ('c', 'C'), ('-', '_')]
+            )
+        );
+    }
+
+    #[test]
+    fn escapes() {
+        assert_parse!(
+            "tr/\\/\\t/-\\\\/",
+            Command::Translit(Address::CURRENT, vec![('/', '-'), ('\t', '\\')])
+        );
+    }
+
+    #[test]
+    fn mismatched() {
+        refute_parse!("tr/abc/xy/");
+        refute_parse!("tr/z-a/a-z/");
+    }
+}
+
+mod increment {
+    use super::*;
+    use crate::ed::cmd::IncFlags;
+
+    #[test]
+    fn default() {
+        assert_parse!(
+            "inc",
+            Command::Increment(Address::CURRENT, 1, IncFlags::default())
+        );
+    }
+
+    #[test]
+    fn dec() {
+        assert_parse!(
+            "%dec 5 g #2",
+            Command::Increment(
+                Address::FULL,
+                -5,
+                IncFlags {
+                    sequential: true,
+                    nth: 2
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn zeroth() {
+        refute_parse!("inc #0");
+        refute_parse!("inc #99999999999999999999");
+    }
+}
+
+mod branch {
+    use super::*;
+
+    #[test]
+    fn label() {
+        assert_parse!(":loop", Command::Label("loop".to_string()));
+        refute_parse!("1:loop");
+    }
+
+    #[test]
+    fn unconditional() {
+        assert_parse!("b", Command::Branch(None, false, None));
+        assert_parse!(
+            "b end",
+            Command::Branch(None, false, Some("end".to_string()))
+        );
+    }
+
+    #[test]
+    fn replaced() {
+        assert_parse!(
+            "bt again",
+            Command::Branch(None, true, Some("again".to_string()))
+        );
+    }
+
+    #[test]
+    fn address() {
+        assert_parse!(
+            "/done/b skip",
+            Command::Branch(
+                Some(Address::Line(Offset::Nil(Point::Ref(re!("done"))))),
+                false,
+                Some("skip".to_string())
+            )
+        );
+    }
+}
+
+mod source {
+    use super::*;
+
+    #[test]
+    fn file() {
+        assert_parse!(
+            "so fix.er",
+            Command::Source(SysPoint::File("fix.er".to_string()))
+        );
+        assert_parse!(
+            "source fix.er",
+            Command::Source(SysPoint::File("fix.er".to_string()))
+        );
+    }
+
+    #[test]
+    fn command() {
+        assert_parse!(
+            "so !gen-fixes",
+            Command::Source(SysPoint::Command(Cmd::System("gen-fixes".to_string())))
+        );
+    }
+
+    #[test]
+    fn missing() {
+        refute_parse!("so");
+        refute_parse!("so ");
+        refute_parse!("1so fix.er");
+    }
+}
+
+mod journal {
+    use super::*;
+
+    #[test]
+    fn file() {
+        assert_parse!(
+            "journal fix.er",
+            Command::Journal(SysPoint::File("fix.er".to_string()))
+        );
+    }
+
+    #[test]
+    fn command() {
+        assert_parse!(
+            "journal !tee fix.er",
+            Command::Journal(SysPoint::Command(Cmd::System("tee fix.er".to_string())))
+        );
+    }
+
+    #[test]
+    fn missing() {
+        refute_parse!("journal");
+        refute_parse!("1journal fix.er");
+    }
+}
+
+mod set {
+    use super::*;
+    use crate::Setting;
+
+    #[test]
+    fn value() {
+        assert_parse!(
+            "set indent=tabs",
+            Command::Set(Setting::Indent, "tabs".to_string())
+        );
+
+        assert_parse!(
+            "set writehook=rustfmt --edition 2018",
+            Command::Set(Setting::WriteHook, "rustfmt --edition 2018".to_string())
+        );
+
+        assert_parse!(
+            "set prompt=> ",
+            Command::Set(Setting::Prompt, "> ".to_string())
+        );
+
+        assert_parse!(
+            "set prompt=\"er> \" ",
+            Command::Set(Setting::Prompt, "er> ".to_string())
+        );
+
+        assert_parse!(
+            "set writehook=",
+            Command::Set(Setting::WriteHook, String::new())
+        );
+    }
+
+    #[test]
+    fn query() {
+        assert_parse!("set", Command::Settings(None));
+        assert_parse!(
+            "set wrapsearch?",
+            Command::Settings(Some(Setting::WrapSearch))
+        );
+        assert_parse!("set shiftwidth?", Command::Settings(Some(Setting::Indent)));
+    }
+
+    #[test]
+    fn invalid() {
+        refute_parse!("set nothing=1");
+        refute_parse!("set indent");
+        refute_parse!("set nothing?");
+        refute_parse!("set prompt?x");
+        refute_parse!("1set indent=2");
+        refute_parse!("1set");
+    }
+}
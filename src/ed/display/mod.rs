@@ -0,0 +1,555 @@
+//!
+//! Prints commands back out in the syntax they are parsed from
+//!
+//! The form printed is canonical: defaults are left out, so parsing what is printed gives back
+//! the same command.
+//!
+
+use crate::ed::{
+    cmd::{IncFlags, SortFlags, SubstFlags},
+    prelude::*,
+    re::{Expansion, ReFlags},
+};
+use std::fmt::{self, Display, Formatter, Write};
+
+#[cfg(test)]
+mod test;
+
+/// Delimiters a substitution may use, in order of preference
+const SUBST_SEPS: &str = "/^:?";
+
+impl Display for Point {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Point::Current => f.write_str("."),
+            Point::Last => f.write_str("$"),
+            Point::Abs(line) => write!(f, "{}", line),
+            Point::Mark(mark) => write!(f, "'{}", mark),
+
+            Point::Ref(re) => {
+                delimited(f, re, '/')?;
+                write!(f, "/{}", re.flags())
+            }
+
+            Point::Reb(re) => {
+                delimited(f, re, '?')?;
+                write!(f, "?{}", re.flags())
+            }
+        }
+    }
+}
+
+impl Display for Offset {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Offset::Nil(point) => write!(f, "{}", point),
+            Offset::Relf(point, mag) => write!(f, "{}+{}", point, mag),
+            Offset::Relb(point, mag) => write!(f, "{}-{}", point, mag),
+        }
+    }
+}
+
+impl Display for Address {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Address::Line(offset) => write!(f, "{}", offset),
+            Address::Range { start, end } => write!(f, "{},{}", start, end),
+        }
+    }
+}
+
+impl Display for ReFlags {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        if self.insensitive {
+            f.write_char('I')?;
+        }
+
+        if self.literal {
+            f.write_char('L')?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for Pat {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let exps = match self {
+            Pat::Replay => return f.write_char('%'),
+            Pat::Expansion(exps) => exps,
+        };
+
+        for exp in exps {
+            match exp {
+                Expansion::Lit(lit) if lit == "%" => f.write_str("\\%")?,
+                Expansion::Lit(lit) => {
+                    for ch in lit.chars() {
+                        if ch == '&' || ch == '\\' {
+                            f.write_char('\\')?;
+                        }
+
+                        f.write_char(ch)?;
+                    }
+                }
+
+                Expansion::Whole => f.write_char('&')?,
+                Expansion::Pos(pos) => write!(f, "\\{}", pos)?,
+
+                Expansion::Counter { start: 1, step: 1 } => f.write_str("\\#")?,
+                Expansion::Counter { start, step: 1 } => write!(f, "\\#{{{}}}", start)?,
+                Expansion::Counter { start, step } => write!(f, "\\#{{{},{}}}", start, step)?,
+
+                Expansion::LineNo => f.write_str("\\=")?,
+                Expansion::Filename => f.write_str("\\f")?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for Cmd {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Cmd::Repeat => f.write_str("!!"),
+            Cmd::System(cmd) => write!(f, "!{}", cmd),
+        }
+    }
+}
+
+impl Display for SysPoint {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            SysPoint::Filename => Ok(()),
+            SysPoint::File(name) => f.write_str(name),
+            SysPoint::Command(cmd) => write!(f, "{}", cmd),
+        }
+    }
+}
+
+impl Display for Command {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Command::Print(addr) => write!(f, "{}p", Or(addr, &Address::CURRENT)),
+            Command::Delete(addr) => write!(f, "{}d", Or(addr, &Address::CURRENT)),
+            Command::Yank(addr) => write!(f, "{}y", Or(addr, &Address::CURRENT)),
+            Command::Paste(offset) => write!(f, "{}x", Or(offset, &Offset::CURRENT)),
+            Command::Nop(offset) => write!(f, "{}", offset),
+            Command::Mark(offset, mark) => write!(f, "{}k{}", Or(offset, &Offset::CURRENT), mark),
+
+            Command::Scroll(offset, size) => {
+                write!(f, "{}z", Or(offset, &Offset::CURRENT))?;
+                if let Some(size) = size {
+                    write!(f, "{}", size)?;
+                }
+
+                Ok(())
+            }
+
+            Command::Join(addr) => write!(f, "{}j", Or(addr, &join_default())),
+            Command::JoinWith(addr, sep) => {
+                write!(f, "{}j ", Or(addr, &join_default()))?;
+                str_lit(f, std::slice::from_ref(sep))
+            }
+
+            Command::Move(addr, dest) => write!(
+                f,
+                "{}m{}",
+                Or(addr, &Address::CURRENT),
+                Or(dest, &Offset::CURRENT)
+            ),
+
+            Command::Transfer(addr, dest) => write!(
+                f,
+                "{}t{}",
+                Or(addr, &Address::CURRENT),
+                Or(dest, &Offset::CURRENT)
+            ),
+
+            Command::Subst(addr, re, pat, flags) => {
+                write!(f, "{}s", Or(addr, &Address::CURRENT))?;
+                subst(f, re.as_ref(), pat.as_ref(), flags.as_ref())
+            }
+
+            Command::Write(addr, sink, quit) => {
+                write!(f, "{}w", Or(addr, &Address::FULL))?;
+                if *quit {
+                    f.write_char('q')?;
+                }
+
+                syspoint(f, sink)
+            }
+
+            Command::Read(offset, src) => {
+                write!(f, "{}r", Or(offset, &Offset::Nil(Point::Last)))?;
+                syspoint(f, src)
+            }
+
+            Command::Run(cmd) => write!(f, "{}", cmd),
+
+            Command::Edit(src) => {
+                f.write_char('e')?;
+                syspoint(f, src)
+            }
+
+            Command::Global(addr, re, cmds) => {
+                write!(f, "{}g", Or(addr, &Address::FULL))?;
+                global(f, re.as_ref(), cmds)
+            }
+
+            Command::Void(addr, re, cmds) => {
+                write!(f, "{}v", Or(addr, &Address::FULL))?;
+                global(f, re.as_ref(), cmds)
+            }
+
+            Command::NextBuffer => f.write_str("next"),
+            Command::PrevBuffer => f.write_str("prev"),
+            Command::Quit => f.write_char('q'),
+
+            Command::Append(offset, text) => {
+                write!(f, "{}a", Or(offset, &Offset::CURRENT))?;
+                text_lit(f, text.as_deref())
+            }
+
+            Command::Insert(offset, text) => {
+                write!(f, "{}i", Or(offset, &Offset::CURRENT))?;
+                text_lit(f, text.as_deref())
+            }
+
+            Command::Change(addr, text) => {
+                write!(f, "{}c", Or(addr, &Address::CURRENT))?;
+                text_lit(f, text.as_deref())
+            }
+
+            Command::Sort(addr, flags) => {
+                write!(f, "{}sort", Or(addr, &Address::FULL))?;
+                sort_flags(f, flags)
+            }
+
+            Command::Uniq(addr, insensitive) => {
+                write!(f, "{}uniq", Or(addr, &Address::FULL))?;
+                if *insensitive {
+                    f.write_str(" i")?;
+                }
+
+                Ok(())
+            }
+
+            Command::Tac(addr) => write!(f, "{}tac", Or(addr, &Address::FULL)),
+
+            Command::Indent(addr, levels) => shift(f, addr, '>', *levels),
+            Command::Dedent(addr, levels) => shift(f, addr, '<', *levels),
+
+            Command::Reflow(addr, width) => {
+                write!(f, "{}fmt", Or(addr, &Address::CURRENT))?;
+                if let Some(width) = width {
+                    write!(f, " {}", width)?;
+                }
+
+                Ok(())
+            }
+
+            Command::Align(addr, re, every) => {
+                write!(f, "{}align", Or(addr, &Address::FULL))?;
+                slashed(f, re.as_ref(), if *every { "g" } else { "" })
+            }
+
+            Command::Split(addr, re, keep) => {
+                write!(f, "{}split", Or(addr, &Address::CURRENT))?;
+                slashed(f, re.as_ref(), if *keep { "k" } else { "" })
+            }
+
+            Command::Translit(addr, pairs) => {
+                write!(f, "{}tr/", Or(addr, &Address::CURRENT))?;
+                char_set(f, pairs.iter().map(|(from, _)| *from))?;
+                f.write_char('/')?;
+                char_set(f, pairs.iter().map(|(_, to)| *to))?;
+                f.write_char('/')
+            }
+
+            Command::Increment(addr, amount, flags) => {
+                write!(f, "{}", Or(addr, &Address::CURRENT))?;
+                increment(f, *amount, flags)
+            }
+
+            Command::Label(name) => write!(f, ":{}", name),
+
+            Command::Branch(addr, test, name) => {
+                if let Some(addr) = addr {
+                    write!(f, "{}", addr)?;
+                }
+
+                f.write_char('b')?;
+                if *test {
+                    f.write_char('t')?;
+                }
+
+                if let Some(name) = name {
+                    write!(f, " {}", name)?;
+                }
+
+                Ok(())
+            }
+
+            Command::Source(src) => write!(f, "so {}", src),
+            Command::Journal(dest) => write!(f, "journal {}", dest),
+            Command::Set(setting, value) => {
+                write!(f, "set {}=", setting.name())?;
+                set_value(f, value)
+            }
+            Command::Settings(None) => write!(f, "set"),
+            Command::Settings(Some(setting)) => write!(f, "set {}?", setting.name()),
+        }
+    }
+}
+
+/// Prints a value unless it's the default a command would fill in for it
+struct Or<'a, T>(&'a T, &'a T);
+
+impl<T: Display + PartialEq> Display for Or<'_, T> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        if self.0 == self.1 {
+            Ok(())
+        } else {
+            write!(f, "{}", self.0)
+        }
+    }
+}
+
+fn join_default() -> Address {
+    Address::Range {
+        start: Offset::CURRENT,
+        end: Offset::Relf(Point::Current, 1),
+    }
+}
+
+/// Writes a regex up to (but not including) its closing delimiter
+///
+/// Literals are escaped the way `delimited_re` reads them back, regexes only need their bare
+/// delimiters escaped.
+fn delimited(f: &mut Formatter, re: &Re, delim: char) -> fmt::Result {
+    f.write_char(delim)?;
+
+    if re.flags().literal {
+        for ch in re.source().chars() {
+            if ch == delim || ch == '\\' {
+                f.write_char('\\')?;
+            }
+
+            f.write_char(ch)?;
+        }
+
+        return Ok(());
+    }
+
+    let mut escaped = false;
+    for ch in re.source().chars() {
+        if ch == delim && !escaped {
+            f.write_char('\\')?;
+        }
+
+        escaped = ch == '\\' && !escaped;
+        f.write_char(ch)?;
+    }
+
+    Ok(())
+}
+
+/// Writes `/re/` with an optional regex, then any command flags before the regex's own
+fn slashed(f: &mut Formatter, re: Option<&Re>, flags: &str) -> fmt::Result {
+    match re {
+        Some(re) => {
+            delimited(f, re, '/')?;
+            write!(f, "/{}{}", flags, re.flags())
+        }
+
+        None => write!(f, "//{}", flags),
+    }
+}
+
+fn subst(
+    f: &mut Formatter,
+    re: Option<&Re>,
+    pat: Option<&Pat>,
+    flags: Option<&SubstFlags>,
+) -> fmt::Result {
+    if re.is_none() && pat.is_none() && flags.is_none() {
+        return Ok(());
+    }
+
+    let pat = pat.map(|pat| pat.to_string());
+
+    let sep = SUBST_SEPS
+        .chars()
+        .find(|sep| {
+            !re.is_some_and(|re| re.source().contains(*sep))
+                && !pat.as_ref().is_some_and(|pat| pat.contains(*sep))
+        })
+        .unwrap_or('/');
+
+    match re {
+        Some(re) => delimited(f, re, sep)?,
+        None => f.write_char(sep)?,
+    }
+
+    if pat.is_none() && flags.is_none() {
+        return Ok(());
+    }
+
+    write!(f, "{}{}", sep, pat.unwrap_or_default())?;
+
+    if let Some(flags) = flags {
+        f.write_char(sep)?;
+
+        match flags.occurances {
+            0 => f.write_char('g')?,
+            1 => (),
+            n => write!(f, "{}", n)?,
+        }
+
+        if flags.print {
+            f.write_char('p')?;
+        }
+
+        if flags.confirm {
+            f.write_char('c')?;
+        }
+
+        if let Some(re) = re {
+            write!(f, "{}", re.flags())?;
+        }
+    }
+
+    Ok(())
+}
+
+fn global(f: &mut Formatter, re: Option<&Re>, cmds: &[Command]) -> fmt::Result {
+    slashed(f, re, "")?;
+
+    for (i, cmd) in cmds.iter().enumerate() {
+        if i > 0 {
+            f.write_str("\\\n")?;
+        }
+
+        write!(f, "{}", cmd)?;
+    }
+
+    Ok(())
+}
+
+/// Writes the file or command of a command, which is nothing for the current file
+fn syspoint(f: &mut Formatter, point: &SysPoint) -> fmt::Result {
+    match point {
+        SysPoint::Filename => Ok(()),
+        point => write!(f, " {}", point),
+    }
+}
+
+/// Writes a setting's value, quoted when it has spaces at either end or would lose its quotes
+fn set_value(f: &mut Formatter, value: &str) -> fmt::Result {
+    let quoted = value.len() > 1 && value.starts_with('"') && value.ends_with('"');
+
+    if quoted || value.trim() != value {
+        write!(f, "\"{}\"", value)
+    } else {
+        f.write_str(value)
+    }
+}
+
+fn text_lit(f: &mut Formatter, text: Option<&[String]>) -> fmt::Result {
+    match text {
+        Some(lines) => {
+            f.write_char(' ')?;
+            str_lit(f, lines)
+        }
+
+        None => Ok(()),
+    }
+}
+
+/// Writes lines as a quoted string with the lines separated by `\n`
+fn str_lit(f: &mut Formatter, lines: &[String]) -> fmt::Result {
+    f.write_char('"')?;
+
+    for (i, line) in lines.iter().enumerate() {
+        if i > 0 {
+            f.write_str("\\n")?;
+        }
+
+        for ch in line.chars() {
+            if ch == '"' || ch == '\\' {
+                f.write_char('\\')?;
+            }
+
+            f.write_char(ch)?;
+        }
+    }
+
+    f.write_char('"')
+}
+
+fn sort_flags(f: &mut Formatter, flags: &SortFlags) -> fmt::Result {
+    if flags.reverse {
+        f.write_char('!')?;
+    }
+
+    for (set, flag) in &[
+        (flags.insensitive, " i"),
+        (flags.numeric, " n"),
+        (flags.unique, " u"),
+    ] {
+        if *set {
+            f.write_str(flag)?;
+        }
+    }
+
+    if let Some(key) = &flags.key {
+        f.write_char(' ')?;
+        slashed(f, Some(key), "")?;
+    }
+
+    Ok(())
+}
+
+fn shift(f: &mut Formatter, addr: &Address, dir: char, levels: usize) -> fmt::Result {
+    write!(f, "{}{}", Or(addr, &Address::CURRENT), dir)?;
+
+    if levels != 1 {
+        write!(f, "{}", levels)?;
+    }
+
+    Ok(())
+}
+
+/// Writes each character of a transliteration set, escaping anything `tr` reads specially
+fn char_set(f: &mut Formatter, chars: impl Iterator<Item = char>) -> fmt::Result {
+    for ch in chars {
+        match ch {
+            '\n' => f.write_str("\\n")?,
+            '\t' => f.write_str("\\t")?,
+            '-' | '/' | '\\' => write!(f, "\\{}", ch)?,
+            ch => f.write_char(ch)?,
+        }
+    }
+
+    Ok(())
+}
+
+fn increment(f: &mut Formatter, amount: i64, flags: &IncFlags) -> fmt::Result {
+    match amount {
+        1 => f.write_str("inc")?,
+        -1 => f.write_str("dec")?,
+        amount if amount < 0 => write!(f, "dec {}", amount.unsigned_abs())?,
+        amount => write!(f, "inc {}", amount)?,
+    }
+
+    if flags.sequential {
+        f.write_str(" g")?;
+    }
+
+    if flags.nth != 1 {
+        write!(f, " #{}", flags.nth)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,384 @@
+use super::*;
+use crate::ed::{parse::Parsable, re::ReFlags};
+use crate::VALID_MARKS;
+use nom::combinator::all_consuming;
+use quickcheck::{quickcheck, Arbitrary, Gen};
+use std::str::FromStr;
+
+macro_rules! assert_display {
+    ($input: expr, $expected: expr) => {
+        assert_eq!(Command::from_str($input).unwrap().to_string(), $expected);
+    };
+}
+
+#[test]
+fn defaults_left_out() {
+    assert_display!(".p", "p");
+    assert_display!("1,$w", "w");
+    assert_display!("$r", "r");
+    assert_display!(".,.+1j", "j");
+    assert_display!("%g/re/d", "g/re/d");
+    assert_display!("2,5>1", "2,5>");
+    assert_display!(".>3", ">3");
+}
+
+#[test]
+fn shorthands_expanded() {
+    assert_display!("%p", "1,$p");
+    assert_display!(";p", ".,$p");
+    assert_display!("+", ".+1");
+    assert_display!("-3", ".-3");
+    assert_display!("s/a/b/1", "s/a/b/");
+    assert_display!("dec 1", "dec");
+}
+
+#[test]
+fn subst_forms() {
+    assert_display!("s", "s");
+    assert_display!("s/a", "s/a");
+    assert_display!("s//%/", "s//%/");
+    assert_display!("s/a/\\%/", "s/a/\\%/");
+    assert_display!("s:a:x/y:gp", "s^a^x/y^gp");
+    assert_display!("s/(a)/\\1\\&\\#{2,-1}/3cIL", "s/(a)/\\1\\&\\#{2,-1}/3cIL");
+}
+
+#[test]
+fn regexes() {
+    assert_display!("?a\\?b?p", "?a\\?b?p");
+    assert_display!("/a\\\\b\\//Lp", "/a\\\\b\\//Lp");
+    assert_display!("sort! i u /\\d+/I", "sort! i u /\\d+/I");
+    assert_display!("align/=/g", "align/=/g");
+    assert_display!("g/a\\/b/s/\\//x/", "g/a\\/b/s^/^x^");
+    assert_display!("v/\\//d", "v/\\//d");
+}
+
+#[test]
+fn settings_quoted() {
+    assert_display!("set prompt=\"> \"", "set prompt=\"> \"");
+    assert_display!("set prompt= er", "set prompt=\" er\"");
+    assert_display!("set prompt=\"\"er\"\"", "set prompt=\"\"er\"\"");
+    assert_display!("set prompt=\"er", "set prompt=\"er");
+}
+
+#[test]
+fn text_literals() {
+    assert_display!("a 'say \"hi\"'", "a \"say \\\"hi\\\"\"");
+    assert_display!("c \"one\\ntwo\"", "c \"one\\ntwo\"");
+    assert_display!("j ', '", "j \", \"");
+    assert_display!("tr/a-c\\//x\\-z-/", "tr/abc\\//x\\-z\\-/");
+}
+
+#[test]
+fn global() {
+    assert_display!("g/a/s/a/b/\\\n  p", "g/a/s/a/b/\\\np");
+    assert_display!("v//>2", "v//>2");
+}
+
+quickcheck! {
+    fn points_round_trip(point: Point) -> bool {
+        reparse(&point) == Some(point)
+    }
+
+    fn offsets_round_trip(offset: Offset) -> bool {
+        reparse(&offset) == Some(offset)
+    }
+
+    fn addresses_round_trip(addr: Address) -> bool {
+        reparse(&addr) == Some(addr)
+    }
+
+    fn pats_round_trip(pat: Pat) -> bool {
+        Pat::from_str(&pat.to_string()) == Ok(pat)
+    }
+
+    fn commands_round_trip(cmd: Command) -> bool {
+        Command::from_str(&cmd.to_string()) == Ok(cmd)
+    }
+}
+
+fn reparse<T: Parsable + Display>(value: &T) -> Option<T> {
+    let printed = value.to_string();
+
+    let parsed = all_consuming(T::parse)(&printed).ok();
+
+    parsed.map(|(_, value)| value)
+}
+
+fn below(g: &mut Gen, n: usize) -> usize {
+    usize::arbitrary(g) % n
+}
+
+fn word(g: &mut Gen, alphabet: &str, min: usize) -> String {
+    let chars: Vec<char> = alphabet.chars().collect();
+    let len = min + below(g, 4);
+
+    (0..len).map(|_| *g.choose(&chars).unwrap()).collect()
+}
+
+fn re_flags(g: &mut Gen) -> ReFlags {
+    ReFlags {
+        insensitive: bool::arbitrary(g),
+        literal: bool::arbitrary(g),
+    }
+}
+
+/// A regex, which may hold a `/` its delimiters have to escape
+fn regex(g: &mut Gen, flags: ReFlags) -> Re {
+    let source = if flags.literal {
+        word(g, "ab/.*?\\^ ", 1)
+    } else {
+        let anchor = *g.choose(&["", "^"]).unwrap();
+        let repeat = *g.choose(&["", "*", "+", "?", "$"]).unwrap();
+
+        format!("{}{}{}", anchor, word(g, "abxy019 ./", 1), repeat)
+    };
+
+    Re::with_flags(&source, flags).unwrap()
+}
+
+fn flagged(g: &mut Gen) -> Re {
+    let flags = re_flags(g);
+    regex(g, flags)
+}
+
+impl Arbitrary for Point {
+    fn arbitrary(g: &mut Gen) -> Point {
+        match below(g, 6) {
+            0 => Point::Current,
+            1 => Point::Last,
+            2 => Point::Abs(below(g, 100)),
+            3 => Point::Mark(*g.choose(&VALID_MARKS.chars().collect::<Vec<_>>()).unwrap()),
+            4 => Point::Ref(flagged(g)),
+            _ => Point::Reb(flagged(g)),
+        }
+    }
+}
+
+impl Arbitrary for Offset {
+    fn arbitrary(g: &mut Gen) -> Offset {
+        match below(g, 3) {
+            0 => Offset::Nil(Point::arbitrary(g)),
+            1 => Offset::Relf(Point::arbitrary(g), below(g, 10)),
+            _ => Offset::Relb(Point::arbitrary(g), below(g, 10)),
+        }
+    }
+}
+
+impl Arbitrary for Address {
+    fn arbitrary(g: &mut Gen) -> Address {
+        if bool::arbitrary(g) {
+            Address::Line(Offset::arbitrary(g))
+        } else {
+            Address::Range {
+                start: Offset::arbitrary(g),
+                end: Offset::arbitrary(g),
+            }
+        }
+    }
+}
+
+/// Expansions as the parser splits them, so literal text is never next to more literal text
+impl Arbitrary for Pat {
+    fn arbitrary(g: &mut Gen) -> Pat {
+        if below(g, 8) == 0 {
+            return Pat::Replay;
+        }
+
+        let mut exps: Vec<Expansion> = Vec::new();
+
+        for _ in 0..below(g, 5) {
+            let exp = match below(g, 8) {
+                0 => Expansion::Whole,
+                1 => Expansion::Pos(below(g, 10)),
+                2 => Expansion::Counter {
+                    start: i64::arbitrary(g) % 50,
+                    step: i64::arbitrary(g) % 5,
+                },
+                3 => Expansion::LineNo,
+                4 => Expansion::Filename,
+                5 => Expansion::Lit(g.choose(&["&", "%"]).unwrap().to_string()),
+                _ => match exps.last() {
+                    Some(Expansion::Lit(lit)) if lit != "&" && lit != "%" => continue,
+                    _ => Expansion::Lit(word(g, "ab %/", 1)),
+                },
+            };
+
+            exps.push(exp);
+        }
+
+        Pat::Expansion(exps)
+    }
+}
+
+fn syspoint(g: &mut Gen, filename: bool) -> SysPoint {
+    match below(g, 4) {
+        0 if filename => SysPoint::Filename,
+        1 => SysPoint::Command(Cmd::arbitrary(g)),
+        _ => SysPoint::File(word(g, "abc.019", 1)),
+    }
+}
+
+impl Arbitrary for Cmd {
+    fn arbitrary(g: &mut Gen) -> Cmd {
+        if bool::arbitrary(g) {
+            Cmd::Repeat
+        } else {
+            Cmd::System(word(g, "ls -a", 0).trim().to_string())
+        }
+    }
+}
+
+fn text(g: &mut Gen) -> Option<Vec<String>> {
+    if bool::arbitrary(g) {
+        return None;
+    }
+
+    Some((0..=below(g, 3)).map(|_| word(g, "ab \"'", 0)).collect())
+}
+
+fn label(g: &mut Gen) -> String {
+    word(g, "ab_-1", 1)
+}
+
+/// Substitutions the parser can give: flags need a pattern before them and only flags can
+/// give the regex its own flags
+fn subst(g: &mut Gen, bare: bool) -> Command {
+    let stage = if bare { below(g, 3) } else { 2 };
+
+    let re = if bool::arbitrary(g) {
+        let flags = if stage == 2 {
+            re_flags(g)
+        } else {
+            ReFlags::default()
+        };
+
+        Some(regex(g, flags))
+    } else {
+        None
+    };
+
+    let pat = if stage >= 1 {
+        Some(Pat::arbitrary(g))
+    } else {
+        None
+    };
+
+    let flags = if stage == 2 {
+        Some(SubstFlags {
+            print: bool::arbitrary(g),
+            occurances: below(g, 5),
+            confirm: bool::arbitrary(g),
+        })
+    } else {
+        None
+    };
+
+    Command::Subst(Address::arbitrary(g), re, pat, flags)
+}
+
+fn opt_re(g: &mut Gen) -> Option<Re> {
+    if bool::arbitrary(g) {
+        Some(flagged(g))
+    } else {
+        None
+    }
+}
+
+/// Commands which can be run in a global's list, so none that take the rest of the line
+fn listed(g: &mut Gen) -> Command {
+    let addr = Address::arbitrary(g);
+    let offset = Offset::arbitrary(g);
+
+    match below(g, 26) {
+        0 => Command::Print(addr),
+        1 => Command::Scroll(offset, Option::arbitrary(g).map(|n: usize| n % 50)),
+        2 => Command::Delete(addr),
+        3 => Command::Nop(offset),
+        4 => Command::Mark(offset, *g.choose(&['a', 'z', '_']).unwrap()),
+        5 => Command::Join(addr),
+        6 => Command::JoinWith(addr, word(g, "ab, \"", 0)),
+        7 => Command::Split(addr, opt_re(g), bool::arbitrary(g)),
+        8 => Command::Move(addr, offset),
+        9 => Command::Transfer(addr, offset),
+        10 => subst(g, false),
+        11 => Command::Yank(addr),
+        12 => Command::Paste(offset),
+        13 => Command::Append(offset, text(g)),
+        14 => Command::Insert(offset, text(g)),
+        15 => Command::Change(addr, text(g)),
+        16 => Command::Sort(
+            addr,
+            SortFlags {
+                reverse: bool::arbitrary(g),
+                numeric: bool::arbitrary(g),
+                insensitive: bool::arbitrary(g),
+                unique: bool::arbitrary(g),
+                key: opt_re(g),
+            },
+        ),
+        17 => Command::Uniq(addr, bool::arbitrary(g)),
+        18 => Command::Tac(addr),
+        19 => Command::Indent(addr, below(g, 4)),
+        20 => Command::Dedent(addr, below(g, 4)),
+        21 => Command::Reflow(addr, Option::arbitrary(g).map(|n: usize| n % 100)),
+        22 => Command::Align(addr, opt_re(g), bool::arbitrary(g)),
+        23 => {
+            let mut pairs: Vec<(char, char)> = Vec::new();
+            for _ in 0..below(g, 4) {
+                let from = *g.choose(&['a', '-', '/', '\\', '\n', '\t']).unwrap();
+                if pairs.iter().all(|(seen, _)| *seen != from) {
+                    pairs.push((from, *g.choose(&['z', '-', '\t']).unwrap()));
+                }
+            }
+
+            Command::Translit(addr, pairs)
+        }
+        24 => Command::Increment(
+            addr,
+            i64::arbitrary(g) % 1000,
+            IncFlags {
+                sequential: bool::arbitrary(g),
+                nth: 1 + below(g, 3),
+            },
+        ),
+        _ => match below(g, 5) {
+            0 => Command::Label(label(g)),
+            1 => Command::Branch(Option::arbitrary(g), bool::arbitrary(g), None),
+            2 => Command::Branch(Option::arbitrary(g), bool::arbitrary(g), Some(label(g))),
+            3 => Command::NextBuffer,
+            _ => Command::Quit,
+        },
+    }
+}
+
+impl Arbitrary for Command {
+    fn arbitrary(g: &mut Gen) -> Command {
+        match below(g, 12) {
+            0 => subst(g, true),
+            1 => Command::Write(Address::arbitrary(g), syspoint(g, true), bool::arbitrary(g)),
+            2 => Command::Read(Offset::arbitrary(g), syspoint(g, true)),
+            3 => Command::Run(Cmd::arbitrary(g)),
+            4 => Command::Edit(syspoint(g, true)),
+            5 => Command::Source(syspoint(g, false)),
+            6 => Command::Journal(syspoint(g, false)),
+            7 if bool::arbitrary(g) => Command::PrevBuffer,
+            7 if bool::arbitrary(g) => Command::Settings(
+                Some(*g.choose(crate::Setting::ALL).unwrap()).filter(|_| bool::arbitrary(g)),
+            ),
+            7 => Command::Set(
+                *g.choose(crate::Setting::ALL).unwrap(),
+                word(g, "ab -=1\"", 0),
+            ),
+            8 | 9 => {
+                let cmds = (0..=below(g, 3)).map(|_| listed(g)).collect();
+
+                if bool::arbitrary(g) {
+                    Command::Global(Address::arbitrary(g), opt_re(g), cmds)
+                } else {
+                    Command::Void(Address::arbitrary(g), opt_re(g), cmds)
+                }
+            }
+            _ => listed(g),
+        }
+    }
+}
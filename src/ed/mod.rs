@@ -4,11 +4,17 @@
 
 pub(crate) mod addr;
 pub(crate) mod cmd;
+pub(crate) mod display;
 pub(crate) mod parse;
 pub(crate) mod re;
 pub(crate) mod resolve;
 pub(crate) mod syspoint;
 
+pub use addr::{Address, Offset, Point};
+pub use cmd::{Answer, Command, IncFlags, InvocationError, SortFlags, SubstFlags};
+pub use re::{Expansion, Pat, Re, ReFlags};
+pub use syspoint::{Cmd, SysPoint};
+
 pub(crate) mod prelude {
     use super::*;
 
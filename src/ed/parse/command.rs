@@ -1,20 +1,20 @@
 use crate::ed::{
     addr::{Address, Offset, Point},
-    cmd::Command,
-    cmd::SubstFlags,
-    parse::Parsable,
-    re::{Pat, Re},
+    cmd::{Command, IncFlags, SortFlags},
+    parse::{re_flags::delimited_re, subst_flags::subst_flags, translit::translit, Parsable},
+    re::{Pat, ReFlags},
     syspoint::{Cmd, SysPoint},
 };
-use crate::VALID_MARKS;
+use crate::{Setting, VALID_MARKS};
 use std::str::FromStr;
 
 use nom::{
-    bytes::complete::{escaped, is_not, tag},
-    character::complete::{digit1, multispace0, one_of},
-    combinator::{all_consuming, cond, opt},
+    branch::alt,
+    bytes::complete::{escaped, is_not, tag, take_while1},
+    character::complete::{digit1, multispace0, one_of, space1},
+    combinator::{all_consuming, cond, eof, opt, peek},
     multi::separated_list1,
-    sequence::{delimited, preceded},
+    sequence::{delimited, pair, preceded, terminated},
     IResult,
 };
 
@@ -33,26 +33,207 @@ impl Parsable for Command {
                 return Ok((input, Command::Run(cmd)));
             }
 
-            if let (input, Some(ch)) = opt(one_of("<>e"))(input)? {
-                return match ch {
-                    '>' => Ok((input, Command::NextBuffer)),
-                    '<' => Ok((input, Command::PrevBuffer)),
-
-                    'e' => {
-                        let (input, _) = multispace0(input)?;
-                        let (input, syspoint) = SysPoint::parse(input)?;
-
-                        Ok((input, Command::Edit(syspoint)))
-                    }
+            if let (input, Some(word)) = opt(alt((tag("next"), tag("prev"))))(input)? {
+                return match word {
+                    "next" => Ok((input, Command::NextBuffer)),
+                    "prev" => Ok((input, Command::PrevBuffer)),
 
                     _ => unreachable!(),
                 };
             }
+
+            if let (input, Some(_)) = opt(tag("e"))(input)? {
+                let (input, _) = multispace0(input)?;
+                let (input, syspoint) = SysPoint::parse(input)?;
+
+                return Ok((input, Command::Edit(syspoint)));
+            }
+        }
+
+        if let (input, Some(_)) = opt(tag(":"))(input)? {
+            if addr.is_some() {
+                return Err(nom_bail!(input));
+            }
+
+            let (input, name) = label(input)?;
+
+            return Ok((input, Command::Label(name.to_string())));
+        }
+
+        if let (input, Some(_)) = opt(tag("b"))(input)? {
+            let (input, test) = opt(tag("t"))(input)?;
+            let (input, name) = opt(preceded(space1, label))(input)?;
+
+            let name = name.map(|name| name.to_string());
+
+            return Ok((input, Command::Branch(addr, test.is_some(), name)));
+        }
+
+        if let (input, Some(_)) = opt(pair(alt((tag("source"), tag("so"))), space1))(input)? {
+            let (input, src) = SysPoint::parse(input)?;
+
+            if addr.is_some() || src == SysPoint::Filename {
+                return Err(nom_bail!(input));
+            }
+
+            return Ok((input, Command::Source(src)));
+        }
+
+        if let (input, Some(_)) = opt(pair(tag("journal"), space1))(input)? {
+            let (input, dest) = SysPoint::parse(input)?;
+
+            if addr.is_some() || dest == SysPoint::Filename {
+                return Err(nom_bail!(input));
+            }
+
+            return Ok((input, Command::Journal(dest)));
+        }
+
+        if let (input, Some(_)) = opt(terminated(tag("set"), peek(alt((space1, eof)))))(input)? {
+            if addr.is_some() {
+                return Err(nom_bail!(input));
+            }
+
+            if input.trim().is_empty() {
+                return Ok(("", Command::Settings(None)));
+            }
+
+            let (input, _) = space1(input)?;
+            let (input, name) = label(input)?;
+            let setting = Setting::from_str(name).or(Err(nom_bail!(input)))?;
+
+            if let (input, Some(_)) = opt(tag("?"))(input)? {
+                return Ok((input, Command::Settings(Some(setting))));
+            }
+
+            let (input, _) = tag("=")(input)?;
+
+            // the value is kept as typed, or quoted to keep spaces at the end of a script's line
+            let value = input
+                .trim_end()
+                .strip_prefix('"')
+                .and_then(|value| value.strip_suffix('"'))
+                .unwrap_or(input);
+
+            return Ok(("", Command::Set(setting, value.to_string())));
+        }
+
+        if let (input, Some(_)) = opt(tag("split"))(input)? {
+            let (input, _) = tag("/")(input)?;
+
+            let (input, re_str) = opt(escaped(
+                is_not("\\/"),
+                '\\',
+                one_of("\\.+*?()|[]{}^$?\"/dDwWsS"),
+            ))(input)?;
+
+            let (input, _) = tag("/")(input)?;
+            let (input, keep) = opt(tag("k"))(input)?;
+            let (input, flags) = ReFlags::parse(input)?;
+
+            let re = re_str
+                .map(|re| delimited_re(re, '/', flags))
+                .transpose()
+                .or(Err(nom_bail!(input)))?;
+
+            let addr = addr.unwrap_or(Address::CURRENT);
+
+            return Ok((input, Command::Split(addr, re, keep.is_some())));
+        }
+
+        if let (input, Some(_)) = opt(tag("tr"))(input)? {
+            let (input, pairs) = translit(input)?;
+            let addr = addr.unwrap_or(Address::CURRENT);
+
+            return Ok((input, Command::Translit(addr, pairs)));
+        }
+
+        if let (input, Some(word)) = opt(alt((tag("inc"), tag("dec"))))(input)? {
+            let (input, amount) = opt(preceded(multispace0, digit1))(input)?;
+            let amount: i64 = amount
+                .map(|s| s.parse().or(Err(nom_bail!(input))))
+                .transpose()?
+                .unwrap_or(1);
+
+            let (input, flags) = IncFlags::parse(input)?;
+            let amount = if word == "dec" { -amount } else { amount };
+            let addr = addr.unwrap_or(Address::CURRENT);
+
+            return Ok((input, Command::Increment(addr, amount, flags)));
+        }
+
+        if let (input, Some(_)) = opt(tag("align"))(input)? {
+            let (input, _) = tag("/")(input)?;
+
+            let (input, re_str) = opt(escaped(
+                is_not("\\/"),
+                '\\',
+                one_of("\\.+*?()|[]{}^$?\"/dDwWsS"),
+            ))(input)?;
+
+            let (input, _) = tag("/")(input)?;
+            let (input, every) = opt(tag("g"))(input)?;
+            let (input, flags) = ReFlags::parse(input)?;
+
+            let re = re_str
+                .map(|re| delimited_re(re, '/', flags))
+                .transpose()
+                .or(Err(nom_bail!(input)))?;
+
+            let addr = addr.unwrap_or(Address::FULL);
+
+            return Ok((input, Command::Align(addr, re, every.is_some())));
+        }
+
+        if let (input, Some(_)) = opt(tag("fmt"))(input)? {
+            let (input, width) = opt(preceded(multispace0, digit1))(input)?;
+            let width = width
+                .map(|s| s.parse().or(Err(nom_bail!(input))))
+                .transpose()?;
+
+            let addr = addr.unwrap_or(Address::CURRENT);
+
+            return Ok((input, Command::Reflow(addr, width)));
+        }
+
+        if let (input, Some(word)) = opt(alt((tag("sort"), tag("uniq"), tag("tac"))))(input)? {
+            let addr = addr.unwrap_or(Address::FULL);
+
+            return match word {
+                "sort" => {
+                    let (input, flags) = SortFlags::parse(input)?;
+                    Ok((input, Command::Sort(addr, flags)))
+                }
+
+                "uniq" => {
+                    let (input, flag) = opt(preceded(multispace0, tag("i")))(input)?;
+                    Ok((input, Command::Uniq(addr, flag.is_some())))
+                }
+
+                "tac" => Ok((input, Command::Tac(addr))),
+
+                _ => unreachable!(),
+            };
         }
 
-        let (input, op) = opt(one_of("pdacikjqmtyxswrgvez"))(input)?;
+        let (input, op) = opt(one_of("pdacikjqmtyxswrgvez<>"))(input)?;
 
         match op {
+            Some(shift @ '>') | Some(shift @ '<') => {
+                let addr = addr.unwrap_or(Address::CURRENT);
+                let (input, levels) = opt(digit1)(input)?;
+                let levels = levels
+                    .map(|s| s.parse().or(Err(nom_bail!(input))))
+                    .transpose()?
+                    .unwrap_or(1);
+
+                if shift == '>' {
+                    Ok((input, Command::Indent(addr, levels)))
+                } else {
+                    Ok((input, Command::Dedent(addr, levels)))
+                }
+            }
+
             Some('p') => Ok((input, Command::Print(addr.unwrap_or(Address::CURRENT)))),
 
             Some('z') => {
@@ -70,13 +251,20 @@ impl Parsable for Command {
 
             Some('d') => Ok((input, Command::Delete(addr.unwrap_or(Address::CURRENT)))),
 
-            Some('j') => Ok((
-                input,
-                Command::Join(addr.unwrap_or(Address::Range {
+            Some('j') => {
+                let addr = addr.unwrap_or(Address::Range {
                     start: Offset::CURRENT,
                     end: Offset::Relf(Point::Current, 1),
-                })),
-            )),
+                });
+
+                let (input, sep) = opt(preceded(multispace0, parse_str_lit))(input)?;
+
+                match sep.as_deref() {
+                    Some([sep]) => Ok((input, Command::JoinWith(addr, sep.to_string()))),
+                    Some(_) => Err(nom_bail!(input)),
+                    None => Ok((input, Command::Join(addr))),
+                }
+            }
 
             Some('w') => {
                 let (input, q) = opt(one_of("q"))(input)?;
@@ -196,17 +384,12 @@ impl Parsable for Command {
                     one_of("\\.+*?()|[]{}^$?\"/dDwWsS"),
                 ))(input)?;
 
-                let re = re_str
-                    .map(Re::from_str)
-                    .transpose()
-                    .or(Err(nom_bail!(input)))?;
-
                 let (input, prepat) = opt(one_of(&*sep.to_string()))(input)?;
 
                 let (input, pat_str) = opt(escaped(
                     is_not(&*format!("{}\\", sep)),
                     '\\',
-                    one_of("\\&%"),
+                    one_of("\\&%#=f0123456789"),
                 ))(input)?;
 
                 let pat = if prepat.is_some() {
@@ -217,7 +400,14 @@ impl Parsable for Command {
 
                 let (input, flags_sep) = opt(tag(&*format!("{}", sep)))(input)?;
 
-                let (input, flags) = cond(flags_sep.is_some(), SubstFlags::parse)(input)?;
+                let (input, flags) = cond(flags_sep.is_some(), subst_flags)(input)?;
+                let re_flags = flags.map(|(_, re_flags)| re_flags).unwrap_or_default();
+                let flags = flags.map(|(flags, _)| flags);
+
+                let re = re_str
+                    .map(|re| delimited_re(re, sep, re_flags))
+                    .transpose()
+                    .or(Err(nom_bail!(input)))?;
 
                 let addr = addr.unwrap_or(Address::CURRENT);
                 Ok((input, Command::Subst(addr, re, pat, flags)))
@@ -227,15 +417,16 @@ impl Parsable for Command {
                 let (input, _) = tag("/")(input)?;
 
                 let (input, re_str) = opt(escaped(
-                    is_not("/"),
+                    is_not("\\/"),
                     '\\',
                     one_of("\\.+*?()|[]{}^$?\"/dDwWsS"),
                 ))(input)?;
 
                 let (input, _) = tag("/")(input)?;
+                let (input, flags) = ReFlags::parse(input)?;
 
                 let re = re_str
-                    .map(Re::from_str)
+                    .map(|re| delimited_re(re, '/', flags))
                     .transpose()
                     .or(Err(nom_bail!(input)))?;
 
@@ -253,15 +444,16 @@ impl Parsable for Command {
                 let (input, _) = tag("/")(input)?;
 
                 let (input, re_str) = opt(escaped(
-                    is_not("/"),
+                    is_not("\\/"),
                     '\\',
                     one_of("\\.+*?()|[]{}^$?\"/dDwWsS"),
                 ))(input)?;
 
                 let (input, _) = tag("/")(input)?;
+                let (input, flags) = ReFlags::parse(input)?;
 
                 let re = re_str
-                    .map(Re::from_str)
+                    .map(|re| delimited_re(re, '/', flags))
                     .transpose()
                     .or(Err(nom_bail!(input)))?;
 
@@ -289,6 +481,10 @@ impl Command {
     }
 }
 
+fn label(input: &str) -> IResult<&str, &str> {
+    take_while1(|ch: char| ch.is_alphanumeric() || ch == '_' || ch == '-')(input)
+}
+
 fn parse_str_lit(input: &str) -> IResult<&str, Vec<String>> {
     let (input, end) = one_of("\"'")(input)?;
     let (input, content) = opt(escaped(
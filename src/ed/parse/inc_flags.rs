@@ -0,0 +1,47 @@
+use super::Parsable;
+use crate::ed::cmd::IncFlags;
+
+use nom::{
+    branch::alt,
+    bytes::complete::tag,
+    character::complete::{digit1, multispace0},
+    combinator::{opt, recognize},
+    sequence::{pair, preceded},
+    IResult,
+};
+
+impl Parsable for IncFlags {
+    fn parse(mut input: &str) -> IResult<&str, IncFlags> {
+        let mut flags = IncFlags::default();
+
+        while let (rest, Some(flag)) = opt(preceded(
+            multispace0,
+            alt((tag("g"), recognize(pair(tag("#"), digit1)))),
+        ))(input)?
+        {
+            match flag {
+                "g" => flags.sequential = true,
+                nth => {
+                    flags.nth =
+                        nth[1..]
+                            .parse()
+                            .or(Err(nom::Err::Error(nom::error::Error::new(
+                                input,
+                                nom::error::ErrorKind::Fix,
+                            ))))?
+                }
+            }
+
+            input = rest;
+        }
+
+        if flags.nth == 0 {
+            return Err(nom::Err::Error(nom::error::Error::new(
+                input,
+                nom::error::ErrorKind::Fix,
+            )));
+        }
+
+        Ok((input, flags))
+    }
+}
@@ -1,7 +1,11 @@
 mod cmd;
 mod command;
+mod inc_flags;
+mod re_flags;
+mod sort_flags;
 mod subst_flags;
 mod syspoint;
+mod translit;
 
 mod address;
 mod offset;
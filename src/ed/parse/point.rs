@@ -1,4 +1,4 @@
-use super::Parsable;
+use super::{re_flags::delimited_re, Parsable};
 use crate::VALID_MARKS;
 use nom::{
     branch::alt,
@@ -8,8 +8,7 @@ use nom::{
 };
 
 use crate::ed::addr::Point;
-use crate::ed::re::Re;
-use std::str::FromStr;
+use crate::ed::re::{Re, ReFlags};
 
 const FRE_ESCAPES: &str = "\\.+*?()|[]{}^$?\"/dDwWsS";
 const BRE_ESCAPES: &str = "\\.+*?()|[]{}^$?\"dDwWsS";
@@ -36,18 +35,29 @@ impl Parsable for Point {
             '?' => {
                 let (input, re) = escaped(is_not("\\?"), '\\', one_of(BRE_ESCAPES))(input)?;
                 let (input, _) = tag("?")(input)?;
+                let (input, flags) = ReFlags::parse(input)?;
 
-                let re = re.to_string().replace("\\?", "?");
+                let re = if flags.literal {
+                    delimited_re(re, '?', flags)
+                } else {
+                    Re::with_flags(&re.replace("\\?", "?"), flags)
+                };
 
-                let re = Re::from_str(&re).expect("figure out a way to pass regex err");
+                let re = re.or(Err(nom::Err::Error(nom::error::Error::new(
+                    input,
+                    nom::error::ErrorKind::Fix,
+                ))))?;
                 Ok((input, Point::Reb(re)))
             }
 
             '/' => {
                 let (input, re) = escaped(is_not("\\/"), '\\', one_of(FRE_ESCAPES))(input)?;
                 let (input, _) = tag("/")(input)?;
+                let (input, flags) = ReFlags::parse(input)?;
 
-                let re = Re::from_str(&re).expect("figure out a way to pass regex err");
+                let re = delimited_re(re, '/', flags).or(Err(nom::Err::Error(
+                    nom::error::Error::new(input, nom::error::ErrorKind::Fix),
+                )))?;
                 Ok((input, Point::Ref(re)))
             }
 
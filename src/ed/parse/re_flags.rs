@@ -0,0 +1,74 @@
+use super::Parsable;
+use crate::ed::re::{Re, ReFlags};
+
+use nom::{character::complete::one_of, combinator::opt, IResult};
+
+impl Parsable for ReFlags {
+    /// Flags after a regex, where `i` is left alone when it starts an `i` or `inc` command, so
+    /// that `/re/i` still inserts
+    fn parse(mut input: &str) -> IResult<&str, ReFlags> {
+        let mut flags = ReFlags::default();
+
+        while let (rest, Some(flag)) = opt(one_of("iIL"))(input)? {
+            let command = rest.starts_with("nc")
+                || rest
+                    .chars()
+                    .next()
+                    .is_none_or(|next| next.is_whitespace() || "\"'|\\#".contains(next));
+
+            if flag == 'i' && command {
+                break;
+            }
+
+            flags.set(flag);
+            input = rest;
+        }
+
+        Ok((input, flags))
+    }
+}
+
+impl ReFlags {
+    pub(super) fn set(&mut self, flag: char) {
+        match flag {
+            'i' | 'I' => self.insensitive = true,
+            'L' => self.literal = true,
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Builds a regex from the text between two delimiters
+///
+/// A literal pattern only honors escaped delimiters and backslashes, everything else is taken
+/// as is.  A regex keeps its escapes, except on a delimiter like `/` which means nothing to it.
+pub(super) fn delimited_re(content: &str, delim: char, flags: ReFlags) -> Result<Re, regex::Error> {
+    let bare = flags.literal || regex::escape(delim.encode_utf8(&mut [0; 4])).len() == 1;
+
+    let mut buf = String::with_capacity(content.len());
+    let mut chars = content.chars();
+
+    while let Some(ch) = chars.next() {
+        match (ch, chars.clone().next()) {
+            ('\\', Some(next)) if next == delim && bare => {
+                buf.push(next);
+                chars.next();
+            }
+
+            ('\\', Some('\\')) if flags.literal => {
+                buf.push('\\');
+                chars.next();
+            }
+
+            ('\\', Some(next)) => {
+                buf.push('\\');
+                buf.push(next);
+                chars.next();
+            }
+
+            (ch, _) => buf.push(ch),
+        }
+    }
+
+    Re::with_flags(&buf, flags)
+}
@@ -0,0 +1,46 @@
+use super::{re_flags::delimited_re, Parsable};
+use crate::ed::{cmd::SortFlags, re::ReFlags};
+
+use nom::{
+    bytes::complete::{escaped, is_not, tag},
+    character::complete::{multispace0, one_of},
+    combinator::opt,
+    sequence::preceded,
+    IResult,
+};
+
+impl Parsable for SortFlags {
+    fn parse(input: &str) -> IResult<&str, SortFlags> {
+        let mut flags = SortFlags::default();
+
+        let (mut input, bang) = opt(tag("!"))(input)?;
+        flags.reverse = bang.is_some();
+
+        while let (rest, Some(flag)) = opt(preceded(multispace0, one_of("inu")))(input)? {
+            match flag {
+                'i' => flags.insensitive = true,
+                'n' => flags.numeric = true,
+                'u' => flags.unique = true,
+                _ => unreachable!(),
+            }
+
+            input = rest;
+        }
+
+        let (input, key) = opt(preceded(multispace0, tag("/")))(input)?;
+        if key.is_none() {
+            return Ok((input, flags));
+        }
+
+        let (input, re) = escaped(is_not("\\/"), '\\', one_of("\\.+*?()|[]{}^$?\"/dDwWsS"))(input)?;
+        let (input, _) = tag("/")(input)?;
+        let (input, re_flags) = ReFlags::parse(input)?;
+
+        let re = delimited_re(re, '/', re_flags).or(Err(nom::Err::Error(
+            nom::error::Error::new(input, nom::error::ErrorKind::Fix),
+        )))?;
+        flags.key = Some(re);
+
+        Ok((input, flags))
+    }
+}
@@ -1,28 +1,40 @@
-use crate::ed::cmd::SubstFlags;
-
-use super::Parsable;
+use crate::ed::{cmd::SubstFlags, re::ReFlags};
 
 use nom::{
-    branch::{alt, permutation},
+    branch::alt,
     bytes::complete::tag,
-    character::complete::digit1,
-    combinator::opt,
+    character::complete::{digit1, one_of},
+    combinator::{opt, recognize},
     IResult,
 };
 
-impl Parsable for SubstFlags {
-    fn parse(input: &str) -> IResult<&str, SubstFlags> {
-        let (input, (print, occurances, after_print)) =
-            permutation((opt(tag("p")), opt(alt((tag("g"), digit1))), opt(tag("p"))))(input)?;
+/// Parses the flags trailing a substitution, including those meant for its regex
+pub(super) fn subst_flags(mut input: &str) -> IResult<&str, (SubstFlags, ReFlags)> {
+    let mut flags = SubstFlags::default();
+    let mut re_flags = ReFlags::default();
+    let mut occurances = None;
+
+    loop {
+        let (rest, flag) = opt(alt((recognize(one_of("pciIL")), tag("g"), digit1)))(input)?;
+
+        match flag {
+            Some("p") => flags.print = true,
+            Some("c") => flags.confirm = true,
+            Some("i") => re_flags.set('i'),
+            Some("I") => re_flags.set('I'),
+            Some("L") => re_flags.set('L'),
 
-        let occurances = match occurances {
-            Some("g") => 0,
-            Some(digits) => digits.parse().unwrap(),
-            None => 1,
-        };
+            Some(_) if occurances.is_some() => break,
+            Some("g") => occurances = Some(0),
+            Some(digits) => occurances = Some(digits.parse().unwrap()),
 
-        let print = print.or(after_print) == Some("p");
+            None => break,
+        }
 
-        Ok((input, SubstFlags { print, occurances }))
+        input = rest;
     }
+
+    flags.occurances = occurances.unwrap_or(1);
+
+    Ok((input, (flags, re_flags)))
 }
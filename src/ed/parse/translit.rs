@@ -0,0 +1,82 @@
+use nom::{
+    bytes::complete::tag,
+    error::{Error, ErrorKind},
+    IResult,
+};
+
+/// Parses the `/from/to/` sets of a transliteration into the pairs of characters they map
+///
+/// Sets may contain ranges (`a-z`) and the escapes `\n`, `\t`, `\-`, `\/` and `\\`.  Both sets
+/// must have the same number of characters once expanded.
+pub(super) fn translit(input: &str) -> IResult<&str, Vec<(char, char)>> {
+    let (input, _) = tag("/")(input)?;
+    let (input, from) = char_set(input)?;
+    let (input, _) = tag("/")(input)?;
+    let (input, to) = char_set(input)?;
+    let (input, _) = tag("/")(input)?;
+
+    if from.len() != to.len() {
+        return Err(bail(input));
+    }
+
+    let mut pairs: Vec<(char, char)> = Vec::with_capacity(from.len());
+    for (from, to) in from.into_iter().zip(to) {
+        if !pairs.iter().any(|(seen, _)| *seen == from) {
+            pairs.push((from, to));
+        }
+    }
+
+    Ok((input, pairs))
+}
+
+/// Reads a set up to an unescaped `/`, expanding ranges
+fn char_set(input: &str) -> IResult<&str, Vec<char>> {
+    let mut tokens: Vec<(char, bool)> = Vec::new();
+    let mut chars = input.char_indices();
+
+    let rest = loop {
+        match chars.next() {
+            Some((pos, '/')) => break &input[pos..],
+            Some((_, '\\')) => {
+                let ch = match chars.next() {
+                    Some((_, 'n')) => '\n',
+                    Some((_, 't')) => '\t',
+                    Some((_, ch @ '-')) | Some((_, ch @ '/')) | Some((_, ch @ '\\')) => ch,
+                    _ => return Err(bail(input)),
+                };
+
+                tokens.push((ch, true));
+            }
+            Some((_, ch)) => tokens.push((ch, false)),
+            None => return Err(bail(input)),
+        }
+    };
+
+    let mut set = Vec::with_capacity(tokens.len());
+    let mut i = 0;
+
+    while i < tokens.len() {
+        match tokens.get(i + 1..i + 3) {
+            Some([('-', false), (to, _)]) => {
+                let from = tokens[i].0;
+                if from > *to {
+                    return Err(bail(input));
+                }
+
+                set.extend(from..=*to);
+                i += 3;
+            }
+
+            _ => {
+                set.push(tokens[i].0);
+                i += 1;
+            }
+        }
+    }
+
+    Ok((rest, set))
+}
+
+fn bail(input: &str) -> nom::Err<Error<&str>> {
+    nom::Err::Error(Error::new(input, ErrorKind::Fix))
+}
@@ -1,34 +1,91 @@
-use regex::{Captures, Regex};
+use crate::ed::cmd::InvocationError;
+use regex::{Captures, Regex, RegexBuilder};
+use std::convert::TryFrom;
 use std::ops::Deref;
 use std::str::FromStr;
+use std::sync::OnceLock;
 
 mod parse;
 #[cfg(test)]
 mod test;
 
-/// Wrapper around Regex for PartialEq in testing
+/// Wrapper around Regex keeping the pattern it was written as, for comparing and printing
 #[derive(Debug, Clone)]
 pub struct Re {
-    #[cfg(test)]
     content: String,
+    flags: ReFlags,
     regex: Regex,
+    folded: OnceLock<Regex>,
+}
+
+/// Flags changing how a regex is compiled
+///
+/// - "I" -> match case insensitively
+/// - "L" -> match the pattern literally rather than as a regex
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct ReFlags {
+    /// Match without regard to case
+    pub insensitive: bool,
+    /// Escape the pattern so it only matches itself
+    pub literal: bool,
 }
 
 impl FromStr for Re {
     type Err = regex::Error;
 
-    #[cfg(test)]
     fn from_str(s: &str) -> Result<Re, regex::Error> {
-        let regex = Regex::from_str(s)?;
-        let content = s.to_string();
-        Ok(Re { regex, content })
+        Re::with_flags(s, ReFlags::default())
     }
+}
 
-    #[cfg(not(test))]
-    fn from_str(s: &str) -> Result<Re, regex::Error> {
-        let regex = Regex::from_str(s)?;
-        Ok(Re { regex })
+impl Re {
+    /// Compile a regex with the given flags
+    pub fn with_flags(s: &str, flags: ReFlags) -> Result<Re, regex::Error> {
+        Ok(Re {
+            content: s.to_string(),
+            flags,
+            regex: compile(s, flags)?,
+            folded: OnceLock::new(),
+        })
+    }
+
+    /// The regex to match with, ignoring case when asked to by the `ignorecase` setting
+    ///
+    /// The case insensitive regex is compiled the first time it's needed and kept after.
+    pub fn matcher(&self, ignore_case: bool) -> &Regex {
+        if !ignore_case || self.flags.insensitive {
+            return &self.regex;
+        }
+
+        self.folded.get_or_init(|| {
+            let flags = ReFlags {
+                insensitive: true,
+                ..self.flags
+            };
+
+            compile(&self.content, flags).unwrap_or_else(|_| self.regex.clone())
+        })
+    }
+
+    /// The flags the regex was compiled with
+    pub fn flags(&self) -> ReFlags {
+        self.flags
     }
+
+    /// The pattern as written, which is the text itself for a literal
+    pub fn source(&self) -> &str {
+        &self.content
+    }
+}
+
+fn compile(s: &str, flags: ReFlags) -> Result<Regex, regex::Error> {
+    if flags.literal {
+        RegexBuilder::new(&regex::escape(s))
+    } else {
+        RegexBuilder::new(s)
+    }
+    .case_insensitive(flags.insensitive)
+    .build()
 }
 
 impl Deref for Re {
@@ -39,10 +96,9 @@ impl Deref for Re {
     }
 }
 
-#[cfg(test)]
 impl PartialEq for Re {
     fn eq(&self, other: &Self) -> bool {
-        self.content.eq(&other.content)
+        self.content.eq(&other.content) && self.flags.eq(&other.flags)
     }
 }
 
@@ -53,6 +109,10 @@ impl PartialEq for Re {
 /// - "&" -> A single whole
 /// - "\1" -> the first subgroup
 /// - "\\\\\1" -> a backslash and then the first subgroup
+/// - "\#" -> a count of replacements made by the command (over every line of a g) starting at 1
+/// - "\#{10,-2}" -> a count starting at 10 and stepping by -2 (the step defaults to 1)
+/// - "\=" -> the number of the line being replaced on
+/// - "\f" -> the current filename
 ///
 /// replay must be the whole string in order to be a replay otherwise it's a literal '%'
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -75,11 +135,37 @@ pub enum Expansion {
 
     /// Replace with a specific subgroup in the capture
     Pos(usize),
+
+    /// Replace with a counter, from a start by a step for each replacement
+    Counter {
+        /// The value given to the first replacement
+        start: i64,
+        /// What is added for each later replacement
+        step: i64,
+    },
+
+    /// Replace with the current line number
+    LineNo,
+
+    /// Replace with the current filename
+    Filename,
+}
+
+/// Where a replacement is happening, for expansions that don't come from the capture
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ExpandCtx<'a> {
+    /// The line the match is on
+    pub lineno: usize,
+    /// How many replacements have already been made by the command, counting those made on
+    /// other lines of a `g`
+    pub count: usize,
+    /// The file being edited
+    pub filename: Option<&'a str>,
 }
 
 impl Pat {
-    /// Expand the pattern out of a capture
-    pub fn expand(&self, captures: &Captures) -> String {
+    /// Expand the pattern out of a capture, failing when a counter can't hold its value
+    pub fn expand(&self, captures: &Captures, ctx: &ExpandCtx) -> Result<String, InvocationError> {
         if let Pat::Expansion(exps) = self {
             let mut buf = String::new();
 
@@ -88,10 +174,21 @@ impl Pat {
                     Expansion::Lit(lit) => buf.push_str(lit),
                     Expansion::Whole => buf.push_str(&captures[0]),
                     Expansion::Pos(i) => buf.push_str(&captures[*i]),
+                    Expansion::Counter { start, step } => {
+                        let value = i64::try_from(ctx.count)
+                            .ok()
+                            .and_then(|count| step.checked_mul(count))
+                            .and_then(|offset| start.checked_add(offset))
+                            .ok_or(InvocationError::CounterOverflow)?;
+
+                        buf.push_str(&value.to_string());
+                    }
+                    Expansion::LineNo => buf.push_str(&ctx.lineno.to_string()),
+                    Expansion::Filename => buf.push_str(ctx.filename.unwrap_or_default()),
                 }
             }
 
-            buf
+            Ok(buf)
         } else {
             unreachable!("replay should never actually expand");
         }
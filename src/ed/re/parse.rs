@@ -2,10 +2,10 @@ use super::*;
 use nom::{
     branch::alt,
     bytes::complete::{is_not, tag},
-    character::complete::one_of,
-    combinator::{all_consuming, eof, map, opt},
+    character::complete::{digit1, one_of},
+    combinator::{all_consuming, eof, map, map_res, opt, recognize},
     multi::many0,
-    sequence::{pair, preceded},
+    sequence::{delimited, pair, preceded},
     IResult,
 };
 
@@ -33,6 +33,31 @@ fn pat_parse(input: &str) -> IResult<&str, Pat> {
         }
     });
 
-    let (input, exps) = many0(alt((wholep, groupp, litp)))(input)?;
+    let ctxp = map(preceded(tag("\\"), one_of("=f")), |e| match e {
+        '=' => Expansion::LineNo,
+        _ => Expansion::Filename,
+    });
+
+    let (input, exps) = many0(alt((wholep, counterp, ctxp, groupp, litp)))(input)?;
     Ok((input, Pat::Expansion(exps)))
 }
+
+fn counterp(input: &str) -> IResult<&str, Expansion> {
+    let (input, _) = tag("\\#")(input)?;
+    let (input, args) = opt(delimited(
+        tag("{"),
+        pair(int, opt(preceded(tag(","), int))),
+        tag("}"),
+    ))(input)?;
+
+    let (start, step) = match args {
+        Some((start, step)) => (start, step.unwrap_or(1)),
+        None => (1, 1),
+    };
+
+    Ok((input, Expansion::Counter { start, step }))
+}
+
+fn int(input: &str) -> IResult<&str, i64> {
+    map_res(recognize(pair(opt(tag("-")), digit1)), str::parse)(input)
+}
@@ -18,6 +18,33 @@ fn operates_like_regex() {
     assert!(plus.is_match("ahhhh"));
 }
 
+#[test]
+fn flags() {
+    let insensitive = Re::with_flags(
+        "hello",
+        ReFlags {
+            insensitive: true,
+            literal: false,
+        },
+    )
+    .unwrap();
+
+    assert!(insensitive.is_match("HeLLo"));
+
+    let literal = Re::with_flags(
+        "a.b",
+        ReFlags {
+            insensitive: false,
+            literal: true,
+        },
+    )
+    .unwrap();
+
+    assert!(literal.is_match("a.b"));
+    assert!(!literal.is_match("axb"));
+    assert_ne!(literal, Re::from_str("a.b").unwrap());
+}
+
 mod pat {
     use super::*;
 
@@ -75,4 +102,43 @@ mod pat {
             "&"
         );
     }
+
+    #[test]
+    fn parse_counter() {
+        assert_eq!(
+            Pat::from_str("\\#. "),
+            Ok(Pat::Expansion(vec![
+                Expansion::Counter { start: 1, step: 1 },
+                Expansion::Lit(". ".to_string())
+            ]))
+        );
+
+        assert_eq!(
+            Pat::from_str("\\#{10,-2}"),
+            Ok(Pat::Expansion(vec![Expansion::Counter {
+                start: 10,
+                step: -2
+            }]))
+        );
+
+        assert_eq!(
+            Pat::from_str("\\#{0}"),
+            Ok(Pat::Expansion(vec![Expansion::Counter {
+                start: 0,
+                step: 1
+            }]))
+        );
+    }
+
+    #[test]
+    fn parse_context() {
+        assert_eq!(
+            Pat::from_str("\\f:\\="),
+            Ok(Pat::Expansion(vec![
+                Expansion::Filename,
+                Expansion::Lit(":".to_string()),
+                Expansion::LineNo
+            ]))
+        );
+    }
 }
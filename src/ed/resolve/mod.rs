@@ -1,41 +1,41 @@
-use crate::{ed::prelude::*, Buffer};
+use crate::{ed::prelude::*, interp::Env, Buffer};
 
 pub trait RangeResolver {
-    fn resolve_range(&self, buffer: &Buffer) -> Option<(usize, usize)>;
+    fn resolve_range(&self, buffer: &Buffer, env: &Env) -> Option<(usize, usize)>;
 }
 
 impl RangeResolver for Address {
-    fn resolve_range(&self, buffer: &Buffer) -> Option<(usize, usize)> {
+    fn resolve_range(&self, buffer: &Buffer, env: &Env) -> Option<(usize, usize)> {
         match self {
             Address::Line(offset) => {
-                let pos = offset.resolve_line(buffer);
+                let pos = offset.resolve_line(buffer, env);
 
                 pos.zip(pos)
             }
 
-            Address::Range { start, end } => {
-                start.resolve_line(buffer).zip(end.resolve_line(buffer))
-            }
+            Address::Range { start, end } => start
+                .resolve_line(buffer, env)
+                .zip(end.resolve_line(buffer, env)),
         }
     }
 }
 
 pub trait LineResolver {
-    fn resolve_line(&self, buffer: &Buffer) -> Option<usize>;
+    fn resolve_line(&self, buffer: &Buffer, env: &Env) -> Option<usize>;
 }
 
 impl LineResolver for Offset {
-    fn resolve_line(&self, buffer: &Buffer) -> Option<usize> {
+    fn resolve_line(&self, buffer: &Buffer, env: &Env) -> Option<usize> {
         match self {
-            Offset::Nil(point) => point.resolve_line(buffer),
-            Offset::Relf(point, offset) => point.resolve_line(buffer).map(|i| i + offset),
-            Offset::Relb(point, offset) => point.resolve_line(buffer).map(|i| i - offset),
+            Offset::Nil(point) => point.resolve_line(buffer, env),
+            Offset::Relf(point, offset) => point.resolve_line(buffer, env).map(|i| i + offset),
+            Offset::Relb(point, offset) => point.resolve_line(buffer, env).map(|i| i - offset),
         }
     }
 }
 
 impl LineResolver for Point {
-    fn resolve_line(&self, buffer: &Buffer) -> Option<usize> {
+    fn resolve_line(&self, buffer: &Buffer, env: &Env) -> Option<usize> {
         match self {
             Point::Current => Some(buffer.cursor()),
             Point::Abs(s) => Some(s.clone()),
@@ -43,6 +43,7 @@ impl LineResolver for Point {
             Point::Last => Some(buffer.len()),
 
             Point::Ref(re) => {
+                let re = re.matcher(env.ignore_case);
                 let mut i = buffer.cursor() + 1;
 
                 while let Some(line) = buffer.line(i) {
@@ -53,10 +54,16 @@ impl LineResolver for Point {
                     i += 1;
                 }
 
+                if env.wrap_search {
+                    return (1..=buffer.cursor())
+                        .find(|&i| buffer.line(i).is_some_and(|line| re.is_match(line)));
+                }
+
                 None
             }
 
             Point::Reb(re) => {
+                let re = re.matcher(env.ignore_case);
                 let mut i = buffer.cursor() - 1;
 
                 while let Some(line) = buffer.line(i) {
@@ -67,6 +74,12 @@ impl LineResolver for Point {
                     i -= 1;
                 }
 
+                if env.wrap_search {
+                    return (buffer.cursor()..=buffer.len())
+                        .rev()
+                        .find(|&i| buffer.line(i).is_some_and(|line| re.is_match(line)));
+                }
+
                 None
             }
         }
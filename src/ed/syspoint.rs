@@ -7,7 +7,7 @@ use std::io::{BufRead, BufReader, Write};
 use std::process::{Command as SysCmd, Stdio};
 
 /// A reference point for a "System" resource
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum SysPoint {
     /// The filename of the current buffer
     Filename,
@@ -20,7 +20,7 @@ pub enum SysPoint {
 }
 
 /// System command to be run via `sh -c <cmd>`
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum Cmd {
     /// Repeat the last cmd run.
     Repeat,
@@ -178,7 +178,7 @@ impl Sourcer for Cmd {
 
 impl Cmd {
     /// Runs command and read stdio
-    pub fn read(&self, env: &Env) -> Result<Vec<u8>, ()> {
+    pub(crate) fn read(&self, env: &Env) -> Result<Vec<u8>, ()> {
         let cmd = self
             .replace_filename(env.filename.as_deref(), env.last_rcmd.as_deref())
             .ok_or(())?;
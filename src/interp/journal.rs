@@ -0,0 +1,80 @@
+use crate::ed::cmd::Command;
+use std::fs::File;
+use std::io::{self, Write};
+
+/// The commands typed in a session, written as a script that can be replayed on other files
+///
+/// Only commands which ran without error are kept.  Commands moving between files, writing or
+/// quitting are left out since they make no sense replayed against another file.  A
+/// substitution which asked before each replacement is kept as the commands making the
+/// replacements that were accepted, so replaying it asks nothing.
+#[derive(Debug, Default)]
+pub struct Journal {
+    lines: Vec<String>,
+    record: Option<File>,
+    confirmed: Option<Vec<Command>>,
+}
+
+impl Journal {
+    /// Also write every command out to a file as it is kept
+    pub fn record_to(&mut self, path: &str) -> io::Result<()> {
+        let mut file = File::create(path)?;
+
+        for line in &self.lines {
+            writeln!(file, "{}", line)?;
+        }
+
+        self.record = Some(file);
+
+        Ok(())
+    }
+
+    /// Forget substitutions accepted while running an earlier command
+    pub(crate) fn begin(&mut self) {
+        self.confirmed = None;
+    }
+
+    /// Note the substitutions a confirmed `s` made, to keep in place of the command running it
+    pub(crate) fn confirmed(&mut self, made: &[Command]) {
+        self.confirmed
+            .get_or_insert_with(Vec::new)
+            .extend(made.iter().cloned());
+    }
+
+    /// Keep a command, or the substitutions it had confirmed since `begin`
+    ///
+    /// Recording to a file stops if writing to it fails.
+    pub(crate) fn keep(&mut self, cmd: &Command) {
+        if !cmd.is_replayable() {
+            return;
+        }
+
+        match self.confirmed.take() {
+            Some(made) => self.keep_all(&made),
+            None => self.keep_all(std::slice::from_ref(cmd)),
+        }
+    }
+
+    /// Keep commands which are known to be replayable
+    pub(crate) fn keep_all(&mut self, cmds: &[Command]) {
+        let start = self.lines.len();
+
+        self.lines.extend(cmds.iter().map(Command::to_string));
+
+        if let Some(file) = &mut self.record {
+            let res = self.lines[start..]
+                .iter()
+                .try_for_each(|line| writeln!(file, "{}", line))
+                .and_then(|_| file.flush());
+
+            if res.is_err() {
+                self.record = None;
+            }
+        }
+    }
+
+    /// The lines of the script kept so far
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
+}
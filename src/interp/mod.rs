@@ -3,15 +3,20 @@ use crate::ed::{
     re::{Pat, Re},
 };
 
-use crate::{interp::write_hook::WriteHook, Buffer};
+use crate::{ed::resolve::RangeResolver, interp::write_hook::WriteHook, Buffer};
 
+use journal::Journal;
 use scratch::{ScratchPad, StdoutScratchPad};
 use std::fs::{File, OpenOptions};
-use std::io::{self, ErrorKind};
+use std::io::{self, ErrorKind, Read};
 
-pub(crate) mod scratch;
+pub(crate) mod journal;
+pub mod scratch;
+pub(crate) mod settings;
 pub(crate) mod write_hook;
 
+pub use settings::Setting;
+
 /// Interprets commands on a buffer
 #[derive(Debug)]
 pub struct Interpreter<S: ScratchPad = StdoutScratchPad> {
@@ -36,6 +41,27 @@ pub struct Env {
     pub(crate) last_rcmd: Option<String>,
     pub(crate) last_wcmd: Option<String>,
     pub(crate) write_hook: WriteHook,
+
+    pub(crate) indent: Indent,
+    pub(crate) text_width: usize,
+    pub(crate) loop_limit: usize,
+    pub(crate) subst_count: usize,
+    pub(crate) substituted: bool,
+    pub(crate) sandboxed: bool,
+    pub(crate) wrap_search: bool,
+    pub(crate) ignore_case: bool,
+    pub(crate) prompt: String,
+    pub(crate) sourcing: Vec<String>,
+    pub(crate) journal: Journal,
+}
+
+/// What a single level of indentation is made of
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Indent {
+    /// One tab per level
+    Tabs,
+    /// A number of spaces per level
+    Spaces(usize),
 }
 
 impl Interpreter {
@@ -71,8 +97,8 @@ impl Interpreter {
         })
     }
 
-    #[cfg(test)]
-    pub(crate) fn from_reader<S: ScratchPad, R: std::io::Read>(r: R) -> io::Result<Interpreter<S>> {
+    /// Creates an interpreter editing what is read from a reader, with no file behind it
+    pub fn from_reader<S: ScratchPad, R: Read>(r: R) -> io::Result<Interpreter<S>> {
         let buffer = Buffer::read(r)?;
         let env = Env::default();
 
@@ -88,19 +114,153 @@ impl Interpreter {
             scratch,
         })
     }
+
+    /// Creates an interpreter editing some text, with no file behind it
+    pub fn from_text<S: ScratchPad>(text: &str) -> Interpreter<S> {
+        Interpreter::from_reader(text.as_bytes()).expect("reading from memory can not fail")
+    }
 }
 
 impl<S> Interpreter<S>
 where
     S: ScratchPad,
 {
+    /// Replaces the scratch pad commands print to, such as with one already set up
+    pub fn with_scratch<T: ScratchPad>(self, scratch: T) -> Interpreter<T> {
+        Interpreter {
+            filelist: self.filelist,
+            filepos: self.filepos,
+            buffer: self.buffer,
+            env: self.env,
+            scratch,
+        }
+    }
+
+    /// The buffer being edited
+    pub fn buffer(&self) -> &Buffer {
+        &self.buffer
+    }
+
+    /// The scratch pad commands print to
+    pub fn scratch(&self) -> &S {
+        &self.scratch
+    }
+
+    /// The scratch pad commands print to, to take what they printed
+    pub fn scratch_mut(&mut self) -> &mut S {
+        &mut self.scratch
+    }
+
+    /// Parses and executes a script, which may be one command or many over several lines
+    ///
+    /// Lines are counted from 1 in errors: a line which doesn't parse gives `SourceParse` and a
+    /// command which fails gives `SourceLine` wrapping the error.
+    pub fn run(&mut self, script: &str) -> Result<bool, InvocationError> {
+        let (lines, cmds): (Vec<usize>, Vec<Command>) = Command::from_content_lines(script)
+            .map_err(|(_, pos)| InvocationError::SourceParse(pos + 1))?
+            .into_iter()
+            .unzip();
+
+        self.exec_script_at(&cmds)
+            .map_err(|(pos, err)| InvocationError::SourceLine(lines[pos] + 1, Box::new(err)))
+    }
+
     /// Executes a command on the given buffer
     pub fn exec(&mut self, cmd: &Command) -> Result<bool, InvocationError> {
+        self.env.subst_count = 0;
         let (res, _) = cmd.invoke(self)?;
 
         Ok(res)
     }
 
+    /// Executes a command a user typed, keeping it in the session's journal if it ran
+    pub fn exec_typed(&mut self, cmd: &Command) -> Result<bool, InvocationError> {
+        self.env.journal.begin();
+        let res = self.exec(cmd)?;
+        self.env.journal.keep(cmd);
+
+        Ok(res)
+    }
+
+    /// Executes a script, following its branches
+    ///
+    /// A substitution replacing nothing is not an error when a `bt` tests it, that is when only
+    /// other substitutions come between it and the `bt`.
+    pub fn exec_script(&mut self, cmds: &[Command]) -> Result<bool, InvocationError> {
+        self.exec_script_at(cmds).map_err(|(_, err)| err)
+    }
+
+    /// Executes a script, giving where it failed along with the error
+    pub(crate) fn exec_script_at(
+        &mut self,
+        cmds: &[Command],
+    ) -> Result<bool, (usize, InvocationError)> {
+        self.exec_script_with(cmds, |_, _| false)
+    }
+
+    /// Executes a script, carrying on past the errors which `skip` accepts given where the
+    /// command is in the script
+    pub(crate) fn exec_script_with(
+        &mut self,
+        cmds: &[Command],
+        mut skip: impl FnMut(usize, &InvocationError) -> bool,
+    ) -> Result<bool, (usize, InvocationError)> {
+        let tested = Command::tested_substitutions(cmds);
+
+        let mut pc = 0;
+        let mut replaced = false;
+        let mut branches = 0;
+
+        self.env.substituted = false;
+
+        while let Some(cmd) = cmds.get(pc) {
+            pc += 1;
+
+            match cmd {
+                Command::Branch(addr, test, label) => {
+                    let resolves = match addr {
+                        Some(addr) => addr.resolve_range(&self.buffer, &self.env).is_some(),
+                        None => true,
+                    };
+
+                    if !resolves || (*test && !replaced) {
+                        continue;
+                    }
+
+                    if *test {
+                        replaced = false;
+                    }
+
+                    branches += 1;
+                    if branches > self.env.loop_limit {
+                        return Err((pc - 1, InvocationError::LoopLimit));
+                    }
+
+                    pc = match label {
+                        Some(label) => Command::find_label(cmds, label)
+                            .ok_or((pc - 1, InvocationError::UnknownLabel))?,
+                        None => cmds.len(),
+                    };
+                }
+
+                cmd => {
+                    let res = self.exec(cmd);
+                    replaced |= std::mem::take(&mut self.env.substituted);
+
+                    match res {
+                        Ok(true) => (),
+                        Ok(false) => return Ok(false),
+                        Err(InvocationError::DidNotReplace) if tested[pc - 1] => (),
+                        Err(err) if skip(pc - 1, &err) => (),
+                        Err(err) => return Err((pc - 1, err)),
+                    }
+                }
+            }
+        }
+
+        Ok(true)
+    }
+
     /// Writes to filename if buffer is dirty
     pub fn ensure_clean(&mut self) -> io::Result<()> {
         if self.buffer.is_dirty() {
@@ -131,6 +291,18 @@ impl Default for Env {
 
         let write_hook = WriteHook::default();
 
+        let indent = Indent::default();
+        let text_width = 72;
+        let loop_limit = 10_000;
+        let subst_count = 0;
+        let substituted = false;
+        let sandboxed = false;
+        let wrap_search = false;
+        let ignore_case = false;
+        let prompt = ":".to_string();
+        let sourcing = Vec::new();
+        let journal = Journal::default();
+
         Env {
             cut,
             filename,
@@ -145,6 +317,36 @@ impl Default for Env {
             last_wcmd,
 
             write_hook,
+
+            indent,
+            text_width,
+            loop_limit,
+            subst_count,
+            substituted,
+            sandboxed,
+            wrap_search,
+            ignore_case,
+            prompt,
+            sourcing,
+            journal,
+        }
+    }
+}
+
+impl Default for Indent {
+    fn default() -> Indent {
+        Indent::Spaces(4)
+    }
+}
+
+impl std::str::FromStr for Indent {
+    type Err = std::num::ParseIntError;
+
+    /// Either "tabs" or the number of spaces
+    fn from_str(s: &str) -> Result<Indent, Self::Err> {
+        match s {
+            "tab" | "tabs" => Ok(Indent::Tabs),
+            width => Ok(Indent::Spaces(width.parse()?)),
         }
     }
 }
@@ -0,0 +1,22 @@
+use super::ScratchPad;
+use std::mem;
+
+/// Holds printed lines back to be shown later, such as by interpreters off the main thread
+#[derive(Debug, Default)]
+pub struct BufferedScratchPad {
+    out: String,
+}
+
+impl ScratchPad for BufferedScratchPad {
+    fn print(&mut self, line: &str) {
+        self.out.push_str(line);
+        self.out.push('\n');
+    }
+}
+
+impl BufferedScratchPad {
+    /// Take everything printed so far
+    pub fn take(&mut self) -> String {
+        mem::take(&mut self.out)
+    }
+}
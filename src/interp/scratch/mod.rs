@@ -1,9 +1,30 @@
+//!
+//! Where commands print to, and how they ask for confirmation
+//!
+
+use crate::ed::cmd::Answer;
+use std::ops::Range;
+
+mod buffered;
 mod stdout;
 mod store;
 
+pub use buffered::BufferedScratchPad;
 pub use stdout::StdoutScratchPad;
-pub use store::StoreScratchPad;
+pub(crate) use store::StoreScratchPad;
 
+/// Takes the lines commands print and answers their questions
 pub trait ScratchPad: Default {
+    /// Show a line printed by a command
     fn print(&mut self, line: &str);
+
+    /// Ask whether the highlighted span of a line should be substituted
+    fn confirm(&mut self, _line: &str, _span: Range<usize>) -> Answer {
+        Answer::Quit
+    }
+
+    /// Whether a command may ask for confirmation here at all
+    fn can_confirm(&self) -> bool {
+        true
+    }
 }
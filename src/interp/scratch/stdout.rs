@@ -1,6 +1,9 @@
 use super::ScratchPad;
-use std::io::{self, Stdout, Write};
+use crate::ed::cmd::Answer;
+use std::io::{self, BufRead, Stdout, Write};
+use std::ops::Range;
 
+/// Prints lines to stdout and asks for confirmation on stdin
 #[derive(Debug)]
 pub struct StdoutScratchPad {
     out: Stdout,
@@ -23,4 +26,30 @@ impl ScratchPad for StdoutScratchPad {
             .write_all(&['\n' as u8])
             .expect("Failed to write to stdout");
     }
+
+    fn confirm(&mut self, line: &str, span: Range<usize>) -> Answer {
+        let pad = line[..span.start].chars().count();
+        let width = line[span].chars().count().max(1);
+
+        self.print(line);
+        self.print(&format!("{}{}", " ".repeat(pad), "^".repeat(width)));
+
+        let stdin = io::stdin();
+        let mut input = stdin.lock();
+
+        loop {
+            let _ = write!(self.out, "replace? [ynaq] ");
+            let _ = self.out.flush();
+
+            let mut response = String::new();
+            match input.read_line(&mut response) {
+                Ok(0) | Err(_) => break Answer::Quit,
+                Ok(_) => (),
+            }
+
+            if let Some(answer) = response.trim().chars().next().and_then(Answer::from_char) {
+                break answer;
+            }
+        }
+    }
 }
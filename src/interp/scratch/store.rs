@@ -31,6 +31,11 @@ impl ScratchPad for StoreScratchPad {
 
         self.lines.push_front(line.to_string());
     }
+
+    // confirming a substitution takes over the screen, which can't happen mid command
+    fn can_confirm(&self) -> bool {
+        false
+    }
 }
 
 impl StoreScratchPad {
@@ -0,0 +1,130 @@
+use super::{write_hook::WriteHook, Env, Indent};
+use crate::ed::cmd::InvocationError;
+use std::str::FromStr;
+
+/// How many lines `z` scrolls when neither it nor the `scroll` setting say otherwise
+pub(crate) const DEFAULT_SCROLL: usize = 22;
+
+/// A setting of an interpreter which can be changed with `set name=value` and shown with
+/// `set name?`
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Setting {
+    /// How many lines `z` scrolls by default
+    Scroll,
+    /// What a level of indentation is: "tabs" or a number of spaces, also set as `shiftwidth`
+    Indent,
+    /// The width `fmt` wraps to by default
+    TextWidth,
+    /// How many branches a script may take before giving up
+    LoopLimit,
+    /// A command files are piped through when written, or nothing to write them as they are
+    WriteHook,
+    /// Whether searching for a line goes round the ends of the buffer
+    WrapSearch,
+    /// Whether regexes match without regard to case, as if given the "I" flag
+    IgnoreCase,
+    /// What the prompt for a command reads, spaces and all
+    Prompt,
+}
+
+impl Setting {
+    /// Every setting, in the order they are listed
+    pub const ALL: &'static [Setting] = &[
+        Setting::Scroll,
+        Setting::Indent,
+        Setting::TextWidth,
+        Setting::LoopLimit,
+        Setting::WriteHook,
+        Setting::WrapSearch,
+        Setting::IgnoreCase,
+        Setting::Prompt,
+    ];
+
+    /// The name a setting is set by
+    pub fn name(self) -> &'static str {
+        match self {
+            Setting::Scroll => "scroll",
+            Setting::Indent => "indent",
+            Setting::TextWidth => "textwidth",
+            Setting::LoopLimit => "looplimit",
+            Setting::WriteHook => "writehook",
+            Setting::WrapSearch => "wrapsearch",
+            Setting::IgnoreCase => "ignorecase",
+            Setting::Prompt => "prompt",
+        }
+    }
+
+    /// The text of a setting's current value, as it would be set
+    pub(crate) fn get(self, env: &Env) -> String {
+        match self {
+            Setting::Scroll => env.scroll.unwrap_or(DEFAULT_SCROLL).to_string(),
+            Setting::Indent => match env.indent {
+                Indent::Tabs => "tabs".to_string(),
+                Indent::Spaces(width) => width.to_string(),
+            },
+            Setting::TextWidth => env.text_width.to_string(),
+            Setting::LoopLimit => env.loop_limit.to_string(),
+            Setting::WriteHook => match &env.write_hook {
+                WriteHook::Id => String::new(),
+                WriteHook::Proc(cmd) => cmd.clone(),
+            },
+            Setting::WrapSearch => switch(env.wrap_search).to_string(),
+            Setting::IgnoreCase => switch(env.ignore_case).to_string(),
+            Setting::Prompt => env.prompt.clone(),
+        }
+    }
+
+    /// Change a setting from the text of its value
+    ///
+    /// Spaces around the value only count for the prompt.
+    pub(crate) fn set(self, env: &mut Env, value: &str) -> Result<(), InvocationError> {
+        let invalid = |_| InvocationError::InvalidValue;
+        let trimmed = value.trim();
+
+        match self {
+            Setting::Scroll => env.scroll = Some(trimmed.parse().map_err(invalid)?),
+            Setting::Indent => env.indent = trimmed.parse().map_err(invalid)?,
+            Setting::TextWidth => env.text_width = trimmed.parse().map_err(invalid)?,
+            Setting::LoopLimit => env.loop_limit = trimmed.parse().map_err(invalid)?,
+            Setting::WriteHook if trimmed.is_empty() => env.write_hook = WriteHook::Id,
+            Setting::WriteHook => env.write_hook = WriteHook::Proc(trimmed.to_string()),
+            Setting::WrapSearch => env.wrap_search = parse_switch(trimmed)?,
+            Setting::IgnoreCase => env.ignore_case = parse_switch(trimmed)?,
+            Setting::Prompt => env.prompt = value.to_string(),
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for Setting {
+    type Err = ();
+
+    fn from_str(name: &str) -> Result<Setting, ()> {
+        if name == "shiftwidth" {
+            return Ok(Setting::Indent);
+        }
+
+        Setting::ALL
+            .iter()
+            .copied()
+            .find(|setting| setting.name() == name)
+            .ok_or(())
+    }
+}
+
+fn switch(on: bool) -> &'static str {
+    if on {
+        "on"
+    } else {
+        "off"
+    }
+}
+
+fn parse_switch(value: &str) -> Result<bool, InvocationError> {
+    match value {
+        "on" | "true" | "yes" => Ok(true),
+        "off" | "false" | "no" => Ok(false),
+        _ => Err(InvocationError::InvalidValue),
+    }
+}
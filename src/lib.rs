@@ -1,6 +1,22 @@
 //!
 //! Text editing like the olde times
 //!
+//! The interpreter can be embedded to edit text in memory, with what commands print collected
+//! by a [`scratch::ScratchPad`]:
+//!
+//! ```
+//! use er_rusted::{ed::InvocationError, scratch::BufferedScratchPad, Interpreter};
+//!
+//! let mut interp = Interpreter::from_text::<BufferedScratchPad>("one\ntwo\n");
+//!
+//! interp.run("%s/o/0/g\n$p").unwrap();
+//! assert_eq!(interp.buffer().lines(), &["0ne", "tw0"]);
+//! assert_eq!(interp.scratch_mut().take(), "tw0\n");
+//!
+//! let err = interp.run("s/x/y/").unwrap_err();
+//! assert_eq!(err, InvocationError::SourceLine(1, Box::new(InvocationError::DidNotReplace)));
+//! ```
+//!
 
 #![warn(missing_docs)]
 
@@ -17,6 +33,6 @@ mod interp;
 pub mod ui;
 
 pub use buffer::Buffer;
-pub use interp::Interpreter;
+pub use interp::{scratch, Interpreter, Setting};
 
 pub(crate) const VALID_MARKS: &str = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ<>_";
@@ -0,0 +1,282 @@
+use crate::{
+    ed::cmd::Command,
+    interp::{scratch::ScratchPad, Env},
+    Interpreter, Setting,
+};
+use eyre::{bail, WrapErr};
+use std::env::{var, var_os};
+use std::fs::read_to_string;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Where a project's own config is kept, relative to where er is run
+const LOCAL: &str = ".errc";
+
+/// Commands run on every interpreter before anything else, read from config files
+///
+/// A config file is a script of ed commands, usually `set name=value` lines.  `ER_WRITE_HOOK`
+/// is taken first, files read later override the ones read before them, and settings given on
+/// the command line override them all.
+#[derive(Debug, Default, Clone)]
+pub struct Config {
+    sources: Vec<(String, Vec<(usize, Command)>)>,
+}
+
+impl Config {
+    /// Take the settings given in the environment, which is only `ER_WRITE_HOOK`
+    pub fn from_env() -> Config {
+        let mut sources = Vec::new();
+
+        if let Ok(hook) = var("ER_WRITE_HOOK") {
+            let cmd = Command::Set(Setting::WriteHook, hook);
+            sources.push(("ER_WRITE_HOOK".to_string(), vec![(0, cmd)]));
+        }
+
+        Config { sources }
+    }
+
+    /// Take the environment, then read the user's `er/config.er` (under `$XDG_CONFIG_HOME` or
+    /// `~/.config`) and the `.errc` in the current directory, skipping either when it doesn't
+    /// exist
+    ///
+    /// The `.errc` comes with whatever project er is run in, so it may not set `writehook`,
+    /// source other files, write files or run programs.
+    pub fn load() -> eyre::Result<Config> {
+        let mut config = Config::from_env();
+
+        if let Some(dir) = config_dir() {
+            config.read(&dir.join("er").join("config.er"), true)?;
+        }
+
+        config.read(Path::new(LOCAL), false)?;
+
+        Ok(config)
+    }
+
+    /// Read config files in order, skipping those which don't exist
+    pub fn from_files(paths: &[impl AsRef<Path>]) -> eyre::Result<Config> {
+        let mut config = Config::default();
+
+        for path in paths {
+            config.read(path.as_ref(), true)?;
+        }
+
+        Ok(config)
+    }
+
+    fn read(&mut self, path: &Path, trusted: bool) -> eyre::Result<()> {
+        let name = path.display().to_string();
+
+        let content = match read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e).wrap_err_with(|| format!("failed to read config {}", name)),
+        };
+
+        let cmds = match Command::from_content_lines(&content) {
+            Ok(cmds) => cmds,
+            Err((_, pos)) => bail!("{}: line {} failed to parse", name, pos + 1),
+        };
+
+        for (pos, cmd) in cmds.iter().filter(|_| !trusted) {
+            if let Some(reason) = forbidden(cmd) {
+                bail!("{}: line {} may not {}", name, pos + 1, reason);
+            }
+        }
+
+        self.sources.push((name, cmds));
+
+        Ok(())
+    }
+
+    /// Change a setting after the files are read, given as `name=value` like the `set` command
+    pub fn set(&mut self, assignment: &str) -> eyre::Result<()> {
+        let (setting, value) = match Command::from_str(&format!("set {}", assignment)) {
+            Ok(Command::Set(setting, value)) => (setting, value),
+            _ => bail!("invalid setting {}", assignment),
+        };
+
+        if setting.set(&mut Env::default(), &value).is_err() {
+            bail!("invalid value for setting {}: {}", setting.name(), value);
+        }
+
+        let cmd = Command::Set(setting, value);
+        self.sources.push(("--set".to_string(), vec![(0, cmd)]));
+
+        Ok(())
+    }
+
+    /// Run the config on an interpreter, giving the file that failed and why
+    pub(crate) fn apply<S: ScratchPad>(&self, interp: &mut Interpreter<S>) -> Result<(), String> {
+        for (name, cmds) in &self.sources {
+            for (pos, cmd) in cmds {
+                interp
+                    .exec(cmd)
+                    .map_err(|err| format!("{}: line {}: {}", name, pos + 1, err))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// What a command does that a config which came with a project may not
+fn forbidden(cmd: &Command) -> Option<&'static str> {
+    match cmd {
+        Command::Set(Setting::WriteHook, _) => Some("set writehook"),
+        Command::Global(_, _, cmds) | Command::Void(_, _, cmds) => cmds.iter().find_map(forbidden),
+        Command::Source(_) => Some("source other files"),
+        cmd if cmd.has_side_effects() => Some("write files or run programs"),
+        _ => None,
+    }
+}
+
+fn config_dir() -> Option<PathBuf> {
+    var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::interp::scratch::BufferedScratchPad;
+    use std::fs::write;
+    use tempfile::TempDir;
+
+    fn config(files: &[(&str, &str)]) -> (TempDir, eyre::Result<Config>) {
+        let dir = TempDir::new().unwrap();
+        let mut paths = Vec::new();
+
+        for (name, content) in files {
+            let path = dir.path().join(name);
+            write(&path, content).unwrap();
+            paths.push(path);
+        }
+
+        paths.insert(1, dir.path().join("missing.er"));
+
+        let config = Config::from_files(&paths);
+        (dir, config)
+    }
+
+    #[test]
+    fn test_override() {
+        let (_dir, config) = config(&[
+            ("config.er", "set indent=tabs\nset textwidth=40\n"),
+            (".errc", "# this project\nset indent=2\n"),
+        ]);
+
+        let mut interp = Interpreter::from_text::<BufferedScratchPad>("a\n");
+        config.unwrap().apply(&mut interp).unwrap();
+
+        assert_eq!(interp.env.indent, crate::interp::Indent::Spaces(2));
+        assert_eq!(interp.env.text_width, 40);
+    }
+
+    #[test]
+    fn test_set() {
+        let (_dir, config) = config(&[("config.er", "set scroll=10\nset wrapsearch=off\n")]);
+        let mut config = config.unwrap();
+
+        config.set("wrapsearch=on").unwrap();
+        config.set("shiftwidth=2").unwrap();
+        assert!(config.set("nothing=1").is_err());
+        assert!(config.set("scroll=many").is_err());
+
+        let mut interp = Interpreter::from_text::<BufferedScratchPad>("a\n");
+        config.apply(&mut interp).unwrap();
+
+        assert_eq!(interp.env.scroll, Some(10));
+        assert!(interp.env.wrap_search);
+        assert_eq!(interp.env.indent, crate::interp::Indent::Spaces(2));
+    }
+
+    #[test]
+    fn test_parse_error() {
+        let (_dir, config) = config(&[("config.er", "set indent=tabs\nset nothing=1\n")]);
+
+        let err = config.unwrap_err().to_string();
+        assert!(
+            err.ends_with("config.er: line 2 failed to parse"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_commands() {
+        let (dir, config) = config(&[("config.er", "set indent=2\n%s/a/b/\n")]);
+        let mut config = config.unwrap();
+
+        let local = dir.path().join(".errc");
+        write(&local, "# this project\ng/b/s/$/!/\n").unwrap();
+        config.read(&local, false).unwrap();
+
+        let mut interp = Interpreter::from_text::<BufferedScratchPad>("a\n");
+        config.apply(&mut interp).unwrap();
+
+        assert_eq!(interp.buffer().lines(), &["b!"]);
+        assert_eq!(interp.env.indent, crate::interp::Indent::Spaces(2));
+    }
+
+    #[test]
+    fn test_untrusted_commands() {
+        let dir = TempDir::new().unwrap();
+        let local = dir.path().join(".errc");
+
+        for (script, reason) in &[
+            (
+                "set indent=2\n!touch owned\n",
+                "write files or run programs",
+            ),
+            ("set indent=2\nw !sh\n", "write files or run programs"),
+            ("set indent=2\ng/x/w out\n", "write files or run programs"),
+            ("set indent=2\nso other.er\n", "source other files"),
+        ] {
+            write(&local, script).unwrap();
+
+            let err = Config::default()
+                .read(&local, false)
+                .unwrap_err()
+                .to_string();
+            assert!(
+                err.ends_with(&format!(".errc: line 2 may not {}", reason)),
+                "{}",
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn test_untrusted_write_hook() {
+        let (dir, config) = config(&[("config.er", "set writehook=rustfmt\n")]);
+        let mut config = config.unwrap();
+
+        let local = dir.path().join(".errc");
+        write(&local, "set indent=2\nset writehook=sh hook.sh\n").unwrap();
+
+        let err = config.read(&local, false).unwrap_err().to_string();
+        assert!(
+            err.ends_with(".errc: line 2 may not set writehook"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_invalid_value() {
+        let (_dir, config) = config(&[("config.er", "set scroll=many\n")]);
+
+        let mut interp = Interpreter::from_text::<BufferedScratchPad>("a\n");
+        let err = config.unwrap().apply(&mut interp).unwrap_err();
+
+        assert!(
+            err.ends_with("config.er: line 1: invalid value for setting"),
+            "{}",
+            err
+        );
+    }
+}
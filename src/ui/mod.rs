@@ -1,12 +1,20 @@
 //! Gives an interface for users to use er-rusted
 
+mod config;
 mod repl;
+mod report;
 mod script;
 mod tui;
+mod vars;
+mod walk;
 
+pub use config::Config;
 pub use repl::Repl;
-pub use script::Script;
+pub use report::ReportFormat;
+pub use script::{ErrorPolicy, Script};
 pub use tui::Tui;
+pub use vars::{Define, Vars};
+pub use walk::Walk;
 
 /// A trait to interact with a user
 pub trait UI: Sized {
@@ -1,7 +1,7 @@
 use super::*;
 
 use crate::{ed::cmd::Command, interp::Interpreter};
-use eyre::WrapErr;
+use eyre::{eyre, WrapErr};
 use rustyline::{error::ReadlineError, Config, EditMode, Editor, Helper};
 use std::str::FromStr;
 
@@ -65,9 +65,25 @@ impl Repl {
         Ok(Repl { interp })
     }
 
+    /// Run a config on the interpreter before anything is typed
+    pub fn configure(&mut self, config: &super::Config) -> eyre::Result<()> {
+        config
+            .apply(&mut self.interp)
+            .map_err(|err| eyre!(err).wrap_err("failed to load config"))
+    }
+
+    /// Record the commands typed to a file as a script as they run
+    pub fn record_to(&mut self, path: &str) -> eyre::Result<()> {
+        self.interp
+            .env
+            .journal
+            .record_to(path)
+            .wrap_err_with(|| format!("failed to record to {}", path))
+    }
+
     fn read_line<T: Helper>(&self, rl: &mut Editor<T>) -> Result<String, LineHandling> {
         use LineHandling::*;
-        let mut line = match rl.readline(":") {
+        let mut line = match rl.readline(&self.interp.env.prompt) {
             Ok(line) => line,
             Err(ReadlineError::Interrupted) => return Err(Next),
             Err(ReadlineError::Eof) => return Err(Quit),
@@ -117,7 +133,7 @@ impl Repl {
             cmd.inject(lines);
         }
 
-        match self.interp.exec(&cmd) {
+        match self.interp.exec_typed(&cmd) {
             Err(_) => InvalidInvocation,
             Ok(true) => Next,
             Ok(false) => Quit,
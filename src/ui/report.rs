@@ -0,0 +1,60 @@
+use crate::ed::cmd::InvocationError;
+use serde::Serialize;
+use std::str::FromStr;
+
+/// How the outcome of a script run is reported
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ReportFormat {
+    /// A JSON document on stdout
+    Json,
+}
+
+/// What happened on every file of a script run
+#[derive(Debug, Default, Serialize)]
+pub(crate) struct Report {
+    pub(crate) files: Vec<FileReport>,
+    pub(crate) changed: usize,
+    pub(crate) failed: usize,
+    pub(crate) untouched: usize,
+}
+
+/// What happened on one file
+#[derive(Debug, Serialize)]
+pub(crate) struct FileReport {
+    pub(crate) file: String,
+    pub(crate) status: &'static str,
+    pub(crate) failures: Vec<Failure>,
+    pub(crate) lines_changed: usize,
+    pub(crate) output: String,
+}
+
+/// A command that failed, given by its (0-based) position in the script
+#[derive(Debug, Serialize)]
+pub(crate) struct Failure {
+    pub(crate) command: usize,
+    pub(crate) error: InvocationError,
+    pub(crate) message: String,
+}
+
+impl Failure {
+    pub(crate) fn new(command: usize, error: InvocationError) -> Failure {
+        let message = error.to_string();
+
+        Failure {
+            command,
+            error,
+            message,
+        }
+    }
+}
+
+impl FromStr for ReportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ReportFormat, String> {
+        match s {
+            "json" => Ok(ReportFormat::Json),
+            _ => Err(format!("expected json but got {:?}", s)),
+        }
+    }
+}
@@ -1,30 +1,80 @@
-use super::UI;
+use super::{
+    report::{Failure, FileReport, Report},
+    Config, ReportFormat, Vars, UI,
+};
 use crate::{
-    ed::cmd::Command,
-    interp::{scratch::StdoutScratchPad, Interpreter},
+    buffer::Buffer,
+    ed::cmd::{Command, InvocationError},
+    interp::{
+        scratch::{BufferedScratchPad, ScratchPad, StdoutScratchPad},
+        Interpreter,
+    },
 };
-use eyre::{bail, WrapErr};
-use std::fs::{copy, read_to_string};
+use eyre::{bail, eyre, WrapErr};
+use regex::Regex;
+use std::collections::BTreeSet;
+use std::fs::{copy, metadata, read, read_to_string, remove_file, write};
+use std::io::{self, stdin, stdout, ErrorKind, Read, Write};
+use std::mem;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, Mutex};
+use std::thread;
+use tempfile::Builder;
+
+/// The filename standing for stdin, which is filtered to stdout
+const STDIN: &str = "-";
 
 /// Run an er script
 pub struct Script {
     files: Vec<String>,
     backup: Option<String>,
     commands: Vec<Command>,
+    policy: ErrorPolicy,
+    no_match_ok: bool,
+    dry_run: bool,
+    jobs: usize,
+    contains: Option<Regex>,
+    atomic: bool,
+    staged: Mutex<Vec<(String, Buffer)>>,
+    report: Option<ReportFormat>,
+    config: Config,
+}
+
+/// What to do when a command fails on a file
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub enum ErrorPolicy {
+    /// Stop running on any more files
+    Abort,
+    /// Leave the file unwritten and go on to the next one
+    #[default]
+    Skip,
+    /// Report the error and go on to the next command
+    Ignore,
+}
+
+/// What became of a file a script ran on
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Outcome {
+    Changed,
+    Failed,
+    Untouched,
 }
 
 impl Script {
     /// Create a script with a filename and run it against files
     pub fn from_file(
         script: &str,
+        vars: &Vars,
         backup: Option<String>,
         files: Vec<String>,
     ) -> eyre::Result<Self> {
         let content = read_to_string(script).wrap_err("failed to read script")?;
 
-        let commands = match Command::from_content(&content) {
+        let commands = match vars.script(&content) {
             Ok(commands) => commands,
-            Err((line, pos)) => bail!("line {} failed to parse command: {}", pos, line),
+            Err((pos, err)) => bail!("line {} {}", pos + 1, err),
         };
 
         for cmd in &commands {
@@ -37,59 +87,886 @@ impl Script {
             commands,
             backup,
             files,
+            policy: ErrorPolicy::default(),
+            no_match_ok: false,
+            dry_run: false,
+            jobs: 1,
+            contains: None,
+            atomic: false,
+            staged: Mutex::default(),
+            report: None,
+            config: Config::default(),
         })
     }
 
     /// Create a script from an expression and run it against files
     pub fn from_expr(
         exprs: Vec<String>,
+        vars: &Vars,
         backup: Option<String>,
         files: Vec<String>,
     ) -> eyre::Result<Self> {
         let mut commands = Vec::new();
         for expr in exprs {
-            match Command::from_expr(&expr) {
-                Ok(cmds) => commands.extend(cmds),
-                Err(_) => bail!("{} is an invalid expression", expr),
-            }
+            let cmds = vars.expr(&expr).map_err(|err| eyre!("{} {}", expr, err))?;
+
+            commands.extend(cmds);
         }
 
         Ok(Script {
             commands,
             backup,
             files,
+            policy: ErrorPolicy::default(),
+            no_match_ok: false,
+            dry_run: false,
+            jobs: 1,
+            contains: None,
+            atomic: false,
+            staged: Mutex::default(),
+            report: None,
+            config: Config::default(),
         })
     }
 }
 
-impl UI for Script {
-    fn run(&mut self) -> eyre::Result<()> {
-        'files: for file in &self.files {
-            let mut interp = Interpreter::new::<StdoutScratchPad>(vec![file.clone()]).unwrap();
+impl Script {
+    /// Decide what happens when a command fails, and whether a substitution replacing nothing
+    /// counts as failing
+    pub fn with_policy(mut self, policy: ErrorPolicy, no_match_ok: bool) -> Self {
+        self.policy = policy;
+        self.no_match_ok = no_match_ok;
+        self
+    }
+
+    /// Print a diff of what would change instead of writing files, failing on any command which
+    /// would write files or run programs itself
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Run files on this many threads at once
+    pub fn jobs(mut self, jobs: usize) -> Self {
+        self.jobs = jobs.max(1);
+        self
+    }
 
-            if let Some(backup) = &self.backup {
-                if let Err(e) = copy(&file, format!("{}.{}", file, backup)) {
-                    eprintln!("failed to backup {}: {}", file, e);
-                    continue;
+    /// Only run on files with a line matching a regex, leaving the rest unwritten
+    pub fn contains(mut self, contains: Option<Regex>) -> Self {
+        self.contains = contains;
+        self
+    }
+
+    /// Write no files unless the script succeeds on every one of them, failing on any command
+    /// which would write files or run programs itself
+    pub fn atomic(mut self, atomic: bool) -> Self {
+        self.atomic = atomic;
+        self
+    }
+
+    /// Report on every file in a format for other programs instead of the usual output
+    pub fn report(mut self, report: Option<ReportFormat>) -> Self {
+        self.report = report;
+        self
+    }
+
+    /// Run a config on each file's interpreter before the script
+    pub fn config(mut self, config: Config) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Whether a file's outcome stops the run on the files after it
+    fn aborts(&self, outcome: Outcome) -> bool {
+        outcome == Outcome::Failed && self.policy == ErrorPolicy::Abort && !self.atomic
+    }
+
+    /// Whether a buffer passes the prefilter, if there is one
+    fn wanted(&self, buffer: &Buffer) -> bool {
+        match &self.contains {
+            Some(re) => (1..=buffer.len())
+                .filter_map(|line| buffer.line(line))
+                .any(|line| re.is_match(line)),
+            None => true,
+        }
+    }
+
+    fn run_file<S: ScratchPad>(&self, file: &str, scratch: &mut S, log: &mut Log) -> Outcome {
+        if file == STDIN {
+            return self.run_stream(stdin().lock(), scratch, log);
+        }
+
+        let mut interp = match Interpreter::new::<S>(vec![file.to_string()]) {
+            Ok(interp) => interp,
+            Err(e) => {
+                log.warn(format!("failed to read {}: {}", file, e));
+                return Outcome::Failed;
+            }
+        };
+
+        if !self.wanted(&interp.buffer) {
+            return Outcome::Untouched;
+        }
+
+        if let Some(backup) = self
+            .backup
+            .as_ref()
+            .filter(|_| !self.dry_run && !self.atomic)
+        {
+            if let Err(e) = copy(file, format!("{}.{}", file, backup)) {
+                log.warn(format!("failed to backup {}: {}", file, e));
+                return Outcome::Failed;
+            }
+        }
+
+        let original = interp.buffer.clone();
+
+        mem::swap(&mut interp.scratch, scratch);
+        let outcome = self.apply(&mut interp, file, log);
+        mem::swap(&mut interp.scratch, scratch);
+
+        if outcome == Outcome::Changed && self.report.is_some() {
+            log.lines_changed = original.changed_lines(&interp.buffer);
+        }
+
+        match outcome {
+            Outcome::Changed if self.dry_run => log.diff(&original, &interp.buffer, file),
+            Outcome::Changed if self.atomic => {
+                let mut staged = self.staged.lock().unwrap();
+                staged.push((file.to_string(), interp.buffer));
+                Outcome::Changed
+            }
+            Outcome::Changed => match interp.ensure_clean() {
+                Ok(()) => Outcome::Changed,
+                Err(_) => {
+                    log.warn(format!("failed to write back to {}", file));
+                    Outcome::Failed
                 }
+            },
+            outcome => outcome,
+        }
+    }
+
+    /// Run the script over a stream like a filter, logging the result (or the input unchanged
+    /// if the script failed)
+    fn run_stream<S: ScratchPad>(
+        &self,
+        input: impl Read,
+        scratch: &mut S,
+        log: &mut Log,
+    ) -> Outcome {
+        let mut interp = match Interpreter::new::<S>(Vec::new()) {
+            Ok(interp) => interp,
+            Err(e) => {
+                log.warn(format!("failed to start on {}: {}", STDIN, e));
+                return Outcome::Failed;
             }
+        };
+
+        interp.buffer = match Buffer::read(input) {
+            Ok(buffer) => buffer,
+            Err(e) => {
+                log.warn(format!("failed to read {}: {}", STDIN, e));
+                return Outcome::Failed;
+            }
+        };
+
+        let original = interp.buffer.clone();
+
+        let outcome = if self.wanted(&interp.buffer) {
+            mem::swap(&mut interp.scratch, scratch);
+            let outcome = self.apply(&mut interp, STDIN, log);
+            mem::swap(&mut interp.scratch, scratch);
+            outcome
+        } else {
+            Outcome::Untouched
+        };
+
+        if outcome == Outcome::Changed && self.report.is_some() {
+            log.lines_changed = original.changed_lines(&interp.buffer);
+        }
+
+        let (outcome, mut result) = match outcome {
+            Outcome::Changed if self.dry_run => return log.diff(&original, &interp.buffer, STDIN),
+            outcome if self.dry_run => return outcome,
+            Outcome::Failed => (Outcome::Failed, original),
+            outcome => (outcome, interp.buffer),
+        };
+
+        let mut out = Vec::new();
+        if let Err(e) = result.write(&mut out) {
+            log.warn(format!("failed to write out {}: {}", STDIN, e));
+            return Outcome::Failed;
+        }
+        log.out.push_str(&String::from_utf8_lossy(&out));
+
+        outcome
+    }
+
+    /// Run the commands on an interpreter's buffer, without writing it anywhere
+    fn apply<S: ScratchPad>(
+        &self,
+        interp: &mut Interpreter<S>,
+        name: &str,
+        log: &mut Log,
+    ) -> Outcome {
+        interp.env.sandboxed = self.dry_run || self.atomic;
 
-            for cmd in &self.commands {
-                match interp.exec(cmd) {
-                    Err(err) => {
-                        eprintln!("{} failed to exec on {}", err, file);
-                        continue 'files;
+        if let Err(err) = self.config.apply(interp) {
+            log.warn(format!("failed to load config on {}: {}", name, err));
+            return Outcome::Failed;
+        }
+
+        let res = interp.exec_script_with(&self.commands, |pos, err| {
+            if self.no_match_ok && *err == InvocationError::DidNotReplace {
+                return true;
+            }
+
+            if self.policy == ErrorPolicy::Ignore {
+                log.warn(format!("{} failed to exec on {} (ignored)", err, name));
+                log.failures.push(Failure::new(pos, err.clone()));
+                return true;
+            }
+
+            false
+        });
+
+        match res {
+            Err((pos, err)) => {
+                log.warn(format!("{} failed to exec on {}", err, name));
+                log.failures.push(Failure::new(pos, err));
+                Outcome::Failed
+            }
+            Ok(true) if interp.buffer.is_dirty() => Outcome::Changed,
+            Ok(_) => Outcome::Untouched,
+        }
+    }
+
+    /// Run every file on the main thread, where commands may prompt
+    fn run_serial(&self, files: &[String]) -> Vec<Option<(Outcome, Log)>> {
+        let mut outcomes = Vec::with_capacity(files.len());
+        let mut scratch = StdoutScratchPad::default();
+
+        for file in files {
+            let mut log = Log::default();
+            let outcome = self.run_file(file, &mut scratch, &mut log);
+            self.flush(&mut log);
+
+            outcomes.push(Some((outcome, log)));
+
+            if self.aborts(outcome) {
+                break;
+            }
+        }
+
+        outcomes.resize_with(files.len(), || None);
+        outcomes
+    }
+
+    /// Run files on a pool of threads, showing their output in the order the files were given
+    fn run_parallel(&self, files: &[String]) -> Vec<Option<(Outcome, Log)>> {
+        let mut outcomes: Vec<Option<(Outcome, Log)>> = Vec::new();
+        outcomes.resize_with(files.len(), || None);
+        let next = AtomicUsize::new(0);
+        let abort = AtomicBool::new(false);
+        let (tx, rx) = mpsc::channel();
+
+        thread::scope(|scope| {
+            for _ in 0..self.jobs.min(files.len()) {
+                let tx = tx.clone();
+                let (next, abort) = (&next, &abort);
+
+                scope.spawn(move || {
+                    let mut scratch = BufferedScratchPad::default();
+
+                    while !abort.load(Ordering::SeqCst) {
+                        let pos = next.fetch_add(1, Ordering::SeqCst);
+                        let file = match files.get(pos) {
+                            Some(file) => file,
+                            None => break,
+                        };
+
+                        let mut log = Log::default();
+                        let outcome = self.run_file(file, &mut scratch, &mut log);
+                        log.out.insert_str(0, &scratch.take());
+
+                        if self.aborts(outcome) {
+                            abort.store(true, Ordering::SeqCst);
+                        }
+
+                        if tx.send((pos, outcome, log)).is_err() {
+                            break;
+                        }
                     }
-                    Ok(true) => (),
-                    Ok(false) => continue 'files,
+                });
+            }
+            drop(tx);
+
+            let mut pending = BTreeSet::new();
+            let mut shown = 0;
+
+            for (pos, outcome, log) in rx {
+                outcomes[pos] = Some((outcome, log));
+                pending.insert(pos);
+
+                while pending.remove(&shown) {
+                    if let Some((_, log)) = &mut outcomes[shown] {
+                        self.flush(log);
+                    }
+                    shown += 1;
+                }
+            }
+
+            for pos in pending {
+                if let Some((_, log)) = &mut outcomes[pos] {
+                    self.flush(log);
                 }
             }
+        });
+
+        outcomes
+    }
+}
+
+impl Script {
+    /// Write every staged buffer out beside its file and only then rename them all over their
+    /// files, so a failure before the renames leaves every file as it was
+    ///
+    /// Should a rename fail, the files already replaced are put back as they were.
+    fn commit(&self) -> eyre::Result<()> {
+        let staged = mem::take(&mut *self.staged.lock().unwrap());
+        let mut temps = Vec::with_capacity(staged.len());
+
+        for (file, mut buffer) in staged {
+            let dir = match Path::new(&file).parent() {
+                Some(dir) if dir != Path::new("") => dir,
+                _ => Path::new("."),
+            };
+
+            let mut temp = Builder::new()
+                .prefix(".er-")
+                .tempfile_in(dir)
+                .wrap_err_with(|| format!("failed to stage {}", file))?;
+
+            buffer
+                .write(temp.as_file_mut())
+                .wrap_err_with(|| format!("failed to stage {}", file))?;
+
+            if let Ok(meta) = metadata(&file) {
+                temp.as_file()
+                    .set_permissions(meta.permissions())
+                    .wrap_err_with(|| format!("failed to stage {}", file))?;
+            }
+
+            let before = match read(&file) {
+                Ok(content) => Some(content),
+                Err(e) if e.kind() == ErrorKind::NotFound => None,
+                Err(e) => return Err(e).wrap_err_with(|| format!("failed to stage {}", file)),
+            };
+
+            temps.push((file, temp, before));
+        }
+
+        if let Some(backup) = self.backup.as_ref() {
+            for (file, _, _) in &temps {
+                copy(file, format!("{}.{}", file, backup))
+                    .wrap_err_with(|| format!("failed to backup {}", file))?;
+            }
+        }
+
+        let mut replaced: Vec<(String, Option<Vec<u8>>)> = Vec::with_capacity(temps.len());
+
+        for (file, temp, before) in temps {
+            if let Err(e) = temp.persist(&file) {
+                let kept: Vec<&str> = replaced
+                    .iter()
+                    .rev()
+                    .filter(|(file, before)| restore(file, before).is_err())
+                    .map(|(file, _)| file.as_str())
+                    .collect();
+
+                if kept.is_empty() {
+                    bail!(
+                        "failed to replace {}: {}, so every file was put back as it was",
+                        file,
+                        e.error
+                    );
+                }
+
+                bail!(
+                    "failed to replace {}: {}, and failed to put back {}, which are left changed",
+                    file,
+                    e.error,
+                    kept.join(" ")
+                );
+            }
+
+            replaced.push((file, before));
+        }
+
+        Ok(())
+    }
+
+    /// Show what a file logged, keeping its output back for the report if there is one
+    fn flush(&self, log: &mut Log) {
+        if self.report.is_none() {
+            let _ = stdout().write_all(mem::take(&mut log.out).as_bytes());
+            let _ = stdout().flush();
+        }
+
+        eprint!("{}", mem::take(&mut log.err));
+    }
+}
+
+/// What running on a file printed, held back so files report in order
+#[derive(Debug, Default)]
+struct Log {
+    out: String,
+    err: String,
+    failures: Vec<Failure>,
+    lines_changed: usize,
+}
+
+impl Log {
+    fn warn(&mut self, msg: String) {
+        self.err.push_str(&msg);
+        self.err.push('\n');
+    }
+
+    /// Log a diff between buffers, which is a change only when they differ
+    fn diff(&mut self, original: &Buffer, updated: &Buffer, name: &str) -> Outcome {
+        let diff = original.diff(updated, name);
+        self.out.push_str(&diff);
+
+        if diff.is_empty() {
+            Outcome::Untouched
+        } else {
+            Outcome::Changed
+        }
+    }
+}
+
+impl UI for Script {
+    fn run(&mut self) -> eyre::Result<()> {
+        let stdin = [STDIN.to_string()];
+        let files = if self.files.is_empty() {
+            &stdin[..]
+        } else {
+            &self.files[..]
+        };
+
+        if self.atomic && !self.dry_run && files.iter().any(|file| file == STDIN) {
+            bail!("stdin can not be written atomically");
+        }
+
+        self.staged.get_mut().unwrap().clear();
+
+        let outcomes = if self.report.is_some() || (self.jobs > 1 && files.len() > 1) {
+            self.run_parallel(files)
+        } else {
+            self.run_serial(files)
+        };
+
+        let listing = |wanted: Outcome| -> Vec<&str> {
+            files
+                .iter()
+                .zip(&outcomes)
+                .filter(|(_, outcome)| outcome_of(outcome) == wanted)
+                .map(|(file, _)| file.as_str())
+                .collect()
+        };
+
+        let changed = listing(Outcome::Changed);
+        let failed = listing(Outcome::Failed);
+        let untouched = listing(Outcome::Untouched);
+
+        // a plain filter over stdin stays quiet like sed does
+        let filtering = files == [STDIN];
+
+        let rolled_back = self.atomic && !self.dry_run && !failed.is_empty();
+
+        for (label, listed) in &[
+            (
+                if rolled_back {
+                    "rolled back"
+                } else {
+                    "changed"
+                },
+                &changed,
+            ),
+            ("failed", &failed),
+            ("untouched", &untouched),
+        ] {
+            if !listed.is_empty() && !filtering {
+                eprintln!("{}: {}", label, listed.join(" "));
+            }
+        }
+
+        if let Some(ReportFormat::Json) = self.report {
+            let mut report = Report {
+                changed: changed.len(),
+                failed: failed.len(),
+                untouched: untouched.len(),
+                ..Report::default()
+            };
 
-            if let Err(_) = interp.ensure_clean() {
-                eprintln!("failed to write back to {}", file);
+            for (file, outcome) in files.iter().zip(outcomes) {
+                let status = match outcome_of(&outcome) {
+                    Outcome::Changed if rolled_back => "rolled back",
+                    Outcome::Changed => "changed",
+                    Outcome::Failed => "failed",
+                    Outcome::Untouched => "untouched",
+                };
+                let log = outcome.map(|(_, log)| log).unwrap_or_default();
+
+                report.files.push(FileReport {
+                    file: file.clone(),
+                    status,
+                    failures: log.failures,
+                    lines_changed: log.lines_changed,
+                    output: log.out,
+                });
             }
+
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        }
+
+        if rolled_back {
+            bail!(
+                "{} of {} files failed, so no files were written",
+                failed.len(),
+                files.len()
+            );
+        }
+
+        if !failed.is_empty() {
+            bail!("{} of {} files failed", failed.len(), files.len());
+        }
+
+        if self.atomic && !self.dry_run {
+            self.commit()?;
         }
 
         Ok(())
     }
 }
+
+/// Put a file back as it was before being replaced
+fn restore(file: &str, before: &Option<Vec<u8>>) -> io::Result<()> {
+    match before {
+        Some(content) => write(file, content),
+        None => remove_file(file),
+    }
+}
+
+/// What became of a file, where one never run on is untouched
+fn outcome_of(outcome: &Option<(Outcome, Log)>) -> Outcome {
+    outcome
+        .as_ref()
+        .map_or(Outcome::Untouched, |(outcome, _)| *outcome)
+}
+
+impl FromStr for ErrorPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ErrorPolicy, String> {
+        match s {
+            "abort" => Ok(ErrorPolicy::Abort),
+            "skip" => Ok(ErrorPolicy::Skip),
+            "ignore" => Ok(ErrorPolicy::Ignore),
+            _ => Err(format!("expected abort, skip or ignore but got {:?}", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    fn file(content: &str) -> NamedTempFile {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(content.as_bytes()).unwrap();
+        file
+    }
+
+    fn run(script: &Script, file: &NamedTempFile) -> Outcome {
+        let path = file.path().display().to_string();
+        script.run_file(
+            &path,
+            &mut BufferedScratchPad::default(),
+            &mut Log::default(),
+        )
+    }
+
+    fn stream(script: &Script, input: &str) -> (Outcome, String) {
+        let mut log = Log::default();
+        let outcome = script.run_stream(
+            input.as_bytes(),
+            &mut BufferedScratchPad::default(),
+            &mut log,
+        );
+
+        (outcome, log.out)
+    }
+
+    fn script(exprs: &[&str]) -> Script {
+        let exprs = exprs.iter().map(|expr| expr.to_string()).collect();
+        Script::from_expr(exprs, &Vars::default(), None, Vec::new()).unwrap()
+    }
+
+    #[test]
+    fn test_policy() {
+        assert_eq!(Ok(ErrorPolicy::Abort), "abort".parse());
+        assert_eq!(Ok(ErrorPolicy::Ignore), "ignore".parse());
+        assert!("maybe".parse::<ErrorPolicy>().is_err());
+    }
+
+    #[test]
+    fn test_outcomes() {
+        let script = script(&["%s/foo/bar/"]);
+        let matching = file("foo\n");
+        let missing = file("baz\n");
+
+        assert_eq!(Outcome::Changed, run(&script, &matching));
+        assert_eq!(Outcome::Failed, run(&script, &missing));
+
+        let script = script.with_policy(ErrorPolicy::Skip, true);
+        assert_eq!(Outcome::Untouched, run(&script, &missing));
+    }
+
+    #[test]
+    fn test_dry_run() {
+        let script = script(&["%s/foo/bar/"]).dry_run(true);
+        let target = file("foo\n");
+
+        assert_eq!(Outcome::Changed, run(&script, &target));
+        assert_eq!("foo\n", read_to_string(target.path()).unwrap());
+    }
+
+    #[test]
+    fn test_dry_run_side_effects() {
+        let target = file("foo\n");
+        let path = target.path().display().to_string();
+        let out = NamedTempFile::new().unwrap();
+        let out_path = out.path().display().to_string();
+
+        for cmd in &[
+            "w",
+            "wq",
+            &format!("w {}", out_path),
+            "w !cat",
+            "r !echo",
+            "!true",
+            "e",
+        ] {
+            let script = script(&["%s/foo/XX/", cmd]).dry_run(true);
+
+            assert_eq!(Outcome::Failed, run(&script, &target), "{}", cmd);
+            assert_eq!(b"foo\n", &std::fs::read(&path).unwrap()[..], "{}", cmd);
+            assert_eq!("", read_to_string(&out_path).unwrap(), "{}", cmd);
+        }
+    }
+
+    #[test]
+    fn test_stream() {
+        let script = script(&["%s/foo/bar/", "$s/$/!/"]);
+
+        assert_eq!(
+            (Outcome::Changed, "bar\nbaz!\n".to_string()),
+            stream(&script, "foo\nbaz\n")
+        );
+    }
+
+    #[test]
+    fn test_expr_inc() {
+        assert_eq!(
+            (Outcome::Changed, "v1.2.4\n".to_string()),
+            stream(&script(&["inc #3"]), "v1.2.3\n")
+        );
+
+        assert_eq!(
+            (Outcome::Changed, "B\n".to_string()),
+            stream(&script(&["# upper", "s/b/B/ # the only line"]), "b\n")
+        );
+    }
+
+    #[test]
+    fn test_expr_counter() {
+        assert_eq!(
+            (Outcome::Changed, "1. a\n2. b\n".to_string()),
+            stream(&script(&["%s/^/\\#. /"]), "a\nb\n")
+        );
+    }
+
+    #[test]
+    fn test_stream_failed() {
+        let script = script(&["%s/nowhere/x/"]);
+
+        assert_eq!(
+            (Outcome::Failed, "foo\n".to_string()),
+            stream(&script, "foo\n")
+        );
+    }
+
+    #[test]
+    fn test_stream_dry_run_failed() {
+        let script = script(&["%s/nowhere/x/"]).dry_run(true);
+
+        assert_eq!((Outcome::Failed, String::new()), stream(&script, "foo\n"));
+    }
+
+    #[test]
+    fn test_ignore() {
+        let script = script(&["/nowhere/d", "%s/foo/bar/"]).with_policy(ErrorPolicy::Ignore, false);
+        let target = file("foo\n");
+
+        assert_eq!(Outcome::Changed, run(&script, &target));
+        assert_eq!("bar\n", read_to_string(target.path()).unwrap());
+    }
+
+    #[test]
+    fn test_contains() {
+        let script = script(&["%s/foo/bar/"]).contains(Some(Regex::new("^skip").unwrap()));
+        let target = file("foo\n");
+
+        assert_eq!(Outcome::Untouched, run(&script, &target));
+        assert_eq!("foo\n", read_to_string(target.path()).unwrap());
+
+        assert_eq!(
+            (Outcome::Untouched, "foo\n".to_string()),
+            stream(&script, "foo\n")
+        );
+    }
+
+    #[test]
+    fn test_atomic() {
+        let good = file("foo\n");
+        let bad = file("baz\n");
+
+        let mut script = script(&["%s/foo/bar/"]).atomic(true);
+        script.files = vec![&good, &bad]
+            .into_iter()
+            .map(|file| file.path().display().to_string())
+            .collect();
+
+        assert!(script.run().is_err());
+        assert_eq!("foo\n", read_to_string(good.path()).unwrap());
+        assert_eq!("baz\n", read_to_string(bad.path()).unwrap());
+
+        let mut script = script.with_policy(ErrorPolicy::Skip, true);
+        assert!(script.run().is_ok());
+        assert_eq!("bar\n", read_to_string(good.path()).unwrap());
+        assert_eq!("baz\n", read_to_string(bad.path()).unwrap());
+    }
+
+    #[test]
+    fn test_atomic_side_effects() {
+        let first = file("foo\n");
+        let second = file("foo\n");
+
+        let mut script = script(&["%s/foo/bar/", "w"]).atomic(true);
+        script.files = vec![&first, &second]
+            .into_iter()
+            .map(|file| file.path().display().to_string())
+            .collect();
+
+        assert!(script.run().is_err());
+        assert_eq!("foo\n", read_to_string(first.path()).unwrap());
+        assert_eq!("foo\n", read_to_string(second.path()).unwrap());
+    }
+
+    #[test]
+    fn test_restore() {
+        let target = file("foo\n");
+        let path = target.path().display().to_string();
+
+        write(&path, "bar\n").unwrap();
+        restore(&path, &Some(b"foo\n".to_vec())).unwrap();
+        assert_eq!("foo\n", read_to_string(&path).unwrap());
+
+        restore(&path, &None).unwrap();
+        assert!(!target.path().exists());
+    }
+
+    #[test]
+    fn test_report() {
+        let script =
+            script(&["/nowhere/d", "%s/foo/bar/", "p"]).with_policy(ErrorPolicy::Ignore, false);
+        let script = script.report(Some(ReportFormat::Json));
+        let target = file("foo\nfoo\nbaz\n");
+
+        let mut log = Log::default();
+        let mut scratch = BufferedScratchPad::default();
+        let path = target.path().display().to_string();
+
+        assert_eq!(
+            Outcome::Changed,
+            script.run_file(&path, &mut scratch, &mut log)
+        );
+        assert_eq!(2, log.lines_changed);
+        assert_eq!("bar\n", scratch.take());
+
+        assert_eq!(1, log.failures.len());
+        assert_eq!(0, log.failures[0].command);
+        assert_eq!(InvocationError::AddressNonResolvable, log.failures[0].error);
+
+        let json = serde_json::to_value(FileReport {
+            file: path,
+            status: "changed",
+            failures: log.failures,
+            lines_changed: log.lines_changed,
+            output: String::new(),
+        })
+        .unwrap();
+
+        assert_eq!(
+            "AddressNonResolvable",
+            json["failures"][0]["error"].as_str().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_large_without_report() {
+        let lines: Vec<String> = (1..=20000).map(|i| i.to_string()).collect();
+        let target = file(&format!("{}\n", lines.join("\n")));
+
+        let mut log = Log::default();
+        let path = target.path().display().to_string();
+        let outcome =
+            script(&["%s/^/x/"]).run_file(&path, &mut BufferedScratchPad::default(), &mut log);
+
+        assert_eq!(Outcome::Changed, outcome);
+        assert_eq!(0, log.lines_changed);
+        assert_eq!(
+            format!("x{}\n", lines.join("\nx")),
+            read_to_string(target.path()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parallel() {
+        let script = script(&["%s/foo/bar/"]).jobs(4);
+        let targets: Vec<NamedTempFile> = (0..10)
+            .map(|i| file(if i % 3 == 0 { "baz\n" } else { "foo\n" }))
+            .collect();
+        let paths: Vec<String> = targets
+            .iter()
+            .map(|file| file.path().display().to_string())
+            .collect();
+
+        let outcomes = script.run_parallel(&paths);
+
+        for (i, (outcome, target)) in outcomes.iter().zip(&targets).enumerate() {
+            if i % 3 == 0 {
+                assert_eq!(Outcome::Failed, outcome_of(outcome));
+                assert_eq!("baz\n", read_to_string(target.path()).unwrap());
+            } else {
+                assert_eq!(Outcome::Changed, outcome_of(outcome));
+                assert_eq!("bar\n", read_to_string(target.path()).unwrap());
+            }
+        }
+    }
+}
@@ -20,7 +20,7 @@ impl Action for RunCmd<'_> {
                 return Ok(());
             }
 
-            match tui.interp.exec(&cmd) {
+            match tui.interp.exec_typed(&cmd) {
                 Ok(false) => {
                     tui.pending_quit = true;
                 }
@@ -8,7 +8,7 @@ impl<'a> DrawCmd for CmdDrawCmd<'a> {
             .queue(cursor::SavePosition)?
             .queue(MoveTo(0, 0))?
             .queue(Clear(ClearType::CurrentLine))?
-            .queue(Print(": "))?
+            .queue(Print(format!("{} ", tui.interp.env.prompt)))?
             .queue(Print(self.0))?
             .queue(cursor::RestorePosition)?;
 
@@ -0,0 +1,28 @@
+use super::*;
+use std::ops::Range;
+
+pub struct ConfirmDrawCmd<'a>(pub &'a str, pub Range<usize>);
+
+impl DrawCmd for ConfirmDrawCmd<'_> {
+    fn draw(&self, tui: &mut Tui) -> crossterm::Result<()> {
+        let line = self.0;
+        let span = self.1.clone();
+
+        tui.stdout
+            .queue(cursor::SavePosition)?
+            .queue(MoveTo(0, 0))?
+            .queue(Clear(ClearType::CurrentLine))?
+            .queue(Print(style("? ").with(Color::Yellow)))?
+            .queue(Print(&line[..span.start]))?
+            .queue(Print(
+                style(&line[span.clone()])
+                    .with(Color::Black)
+                    .on(Color::Yellow),
+            ))?
+            .queue(Print(&line[span.end..]))?
+            .queue(Print(style("  [ynaq]").with(Color::Yellow)))?
+            .queue(cursor::RestorePosition)?;
+
+        Ok(())
+    }
+}
@@ -8,6 +8,7 @@ use crossterm::{
 
 mod buffer;
 mod cmd;
+mod confirm;
 mod cur;
 mod error;
 mod key_buffer;
@@ -16,6 +17,7 @@ mod text;
 
 pub use buffer::BufferDrawCmd;
 pub use cmd::CmdDrawCmd;
+pub use confirm::ConfirmDrawCmd;
 pub use cur::{CursorDrawCmd, ShowCursorDrawCmd};
 pub use error::ErrorDrawCmd;
 pub use key_buffer::KeyBufferDrawCmd;
@@ -1,5 +1,5 @@
-use super::UI;
-use crate::{interp::scratch::StoreScratchPad, interp::write_hook::WriteHook, Interpreter};
+use super::{Config, UI};
+use crate::{interp::scratch::StoreScratchPad, Interpreter};
 use crossterm::{
     cursor::{self, MoveTo},
     event::{read, Event, KeyModifiers},
@@ -8,12 +8,11 @@ use crossterm::{
     QueueableCommand,
 };
 use draw::*;
-use eyre::WrapErr;
+use eyre::{eyre, WrapErr};
 use history::History;
 use lock::WindowLock;
 use mode::{SealedTMode, TMode};
 use motion::Search;
-use std::env::var;
 use std::io::{Stdout, Write};
 
 mod action;
@@ -54,6 +53,22 @@ impl Tui {
         })
     }
 
+    /// Run a config on the interpreter before anything is typed
+    pub fn configure(&mut self, config: &Config) -> eyre::Result<()> {
+        config
+            .apply(&mut self.interp)
+            .map_err(|err| eyre!(err).wrap_err("failed to load config"))
+    }
+
+    /// Record the commands typed to a file as a script as they run
+    pub fn record_to(&mut self, path: &str) -> eyre::Result<()> {
+        self.interp
+            .env
+            .journal
+            .record_to(path)
+            .wrap_err_with(|| format!("failed to record to {}", path))
+    }
+
     fn input_loop(&mut self) -> crossterm::Result<()> {
         let mut tmode = SealedTMode::default();
         loop {
@@ -84,10 +99,6 @@ impl Tui {
 
 impl UI for Tui {
     fn run(&mut self) -> eyre::Result<()> {
-        if let Ok(hook) = var("ER_WRITE_HOOK") {
-            self.interp.env.write_hook = WriteHook::Proc(hook);
-        }
-
         enable_raw_mode()?;
         let hook = std::panic::take_hook();
         std::panic::set_hook(Box::new(move |info| {
@@ -99,7 +110,7 @@ impl UI for Tui {
             .queue(Clear(ClearType::All))?
             .queue(cursor::Hide)?
             .queue(MoveTo(0, 0))?
-            .queue(Print(": "))?;
+            .queue(Print(format!("{} ", self.interp.env.prompt)))?;
 
         BufferDrawCmd.draw(self)?;
 
@@ -103,7 +103,7 @@ impl Cmd {
         self.buffer.clear();
 
         if cmd.needs_text() {
-            if let Some((pos, hide)) = cmd.text_markers(&tui.interp.buffer) {
+            if let Some((pos, hide)) = cmd.text_markers(&tui.interp.buffer, &tui.interp.env) {
                 let next: Text = (pos, hide, cmd).into();
                 next.draw(tui)?;
 
@@ -114,7 +114,19 @@ impl Cmd {
             }
         }
 
-        match tui.interp.exec(&cmd) {
+        let res = match cmd.subst_session(&mut tui.interp) {
+            Ok(Some(session)) => {
+                let next: Confirm = session.into();
+                next.draw(tui)?;
+
+                return Ok(next.into());
+            }
+
+            Ok(None) => tui.interp.exec_typed(&cmd),
+            Err(err) => Err(err),
+        };
+
+        match res {
             Ok(false) => {
                 tui.pending_quit = true;
             }
@@ -0,0 +1,99 @@
+use super::*;
+use crate::ed::cmd::{Answer, InvocationError, SubstSession};
+use crate::ui::tui::action::*;
+use crate::ui::tui::draw::*;
+
+/// Asks about each match of a `s///c` before replacing it
+#[derive(Debug)]
+pub struct Confirm {
+    session: SubstSession,
+}
+
+impl TMode for Confirm {
+    fn process_key(mut self, key: KeyEvent, tui: &mut Tui) -> crossterm::Result<SealedTMode> {
+        let answer = match key.code {
+            KeyCode::Char(ch) => Answer::from_char(ch),
+            KeyCode::Esc => Some(Answer::Quit),
+            _ => None,
+        };
+
+        if let Some(answer) = answer {
+            self.session
+                .answer(answer, &mut tui.interp.buffer, &mut tui.interp.scratch);
+        }
+
+        if self.session.is_done() {
+            return self.finish(tui);
+        }
+
+        self.draw(tui)?;
+
+        Ok(self.into())
+    }
+
+    fn process_ctl_key(mut self, key: KeyEvent, tui: &mut Tui) -> crossterm::Result<SealedTMode> {
+        match key.code {
+            KeyCode::Char('c') => {
+                self.session.answer(
+                    Answer::Quit,
+                    &mut tui.interp.buffer,
+                    &mut tui.interp.scratch,
+                );
+
+                return self.finish(tui);
+            }
+
+            KeyCode::Char('l') => RotateWindowLock::Down.invoke(tui)?,
+            KeyCode::Char('o') => RotateWindowLock::Up.invoke(tui)?,
+            _ => (),
+        }
+
+        self.draw(tui)?;
+
+        Ok(self.into())
+    }
+
+    fn draw(&self, tui: &mut Tui) -> crossterm::Result<()> {
+        if let Some((lineno, line, span)) = self.session.prompt() {
+            tui.interp.buffer.set_cursor(lineno);
+            BufferDrawCmd.draw(tui)?;
+            ConfirmDrawCmd(&line, span).draw(tui)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl From<SubstSession> for Confirm {
+    fn from(session: SubstSession) -> Confirm {
+        Confirm { session }
+    }
+}
+
+impl Confirm {
+    fn finish(self, tui: &mut Tui) -> crossterm::Result<SealedTMode> {
+        if let Some(err) = self.session.error() {
+            BufferDrawCmd.draw(tui)?;
+            ErrorDrawCmd(&format!("{}", err)).draw(tui)?;
+        } else if self.session.replaced() {
+            tui.interp.env.journal.keep_all(self.session.made());
+
+            CmdDrawCmd("").draw(tui)?;
+            BufferDrawCmd.draw(tui)?;
+        } else {
+            BufferDrawCmd.draw(tui)?;
+            ErrorDrawCmd(&format!("{}", InvocationError::DidNotReplace)).draw(tui)?;
+        }
+
+        let next = Cmd::default();
+        if tui.interp.scratch.is_stale() {
+            tui.interp.scratch.refresh();
+
+            let next: Scratch = next.into();
+            next.draw(tui)?;
+            return Ok(next.into());
+        }
+
+        Ok(next.into())
+    }
+}
@@ -138,7 +138,7 @@ impl LineEdit {
         self.buffer.clear();
 
         if cmd.needs_text() {
-            if let Some((pos, hide)) = cmd.text_markers(&tui.interp.buffer) {
+            if let Some((pos, hide)) = cmd.text_markers(&tui.interp.buffer, &tui.interp.env) {
                 let next: Text = (pos, hide, cmd).into();
                 next.draw(tui)?;
 
@@ -149,7 +149,20 @@ impl LineEdit {
             }
         }
 
-        match tui.interp.exec(&cmd) {
+        let res = match cmd.subst_session(&mut tui.interp) {
+            Ok(Some(session)) => {
+                let next: Confirm = session.into();
+                ShowCursorDrawCmd(false).draw(tui)?;
+                next.draw(tui)?;
+
+                return Ok(next.into());
+            }
+
+            Ok(None) => tui.interp.exec_typed(&cmd),
+            Err(err) => Err(err),
+        };
+
+        match res {
             Ok(false) => {
                 tui.pending_quit = true;
             }
@@ -97,7 +97,7 @@ impl LineInsert {
         self.buffer.clear();
 
         if cmd.needs_text() {
-            if let Some((pos, hide)) = cmd.text_markers(&tui.interp.buffer) {
+            if let Some((pos, hide)) = cmd.text_markers(&tui.interp.buffer, &tui.interp.env) {
                 let next: Text = (pos, hide, cmd).into();
                 next.draw(tui)?;
 
@@ -108,7 +108,7 @@ impl LineInsert {
             }
         }
 
-        match tui.interp.exec(&cmd) {
+        match tui.interp.exec_typed(&cmd) {
             Ok(false) => {
                 tui.pending_quit = true;
             }
@@ -3,6 +3,7 @@ use crossterm::event::{KeyCode, KeyEvent};
 use enum_dispatch::enum_dispatch;
 
 mod cmd;
+mod confirm;
 mod key_seq;
 mod line_edit;
 mod line_insert;
@@ -10,6 +11,7 @@ mod scratch;
 mod text;
 
 pub use cmd::Cmd;
+pub use confirm::Confirm;
 pub use line_edit::LineEdit;
 pub use line_insert::LineInsert;
 pub use scratch::Scratch;
@@ -28,6 +30,7 @@ pub trait TMode {
 #[enum_dispatch(TMode)]
 pub enum SealedTMode {
     Cmd,
+    Confirm,
     LineEdit,
     LineInsert,
     Scratch,
@@ -21,7 +21,7 @@ impl TMode for Text {
                     let next = Cmd::default();
                     self.cmd.inject(self.lines);
 
-                    match tui.interp.exec(&self.cmd) {
+                    match tui.interp.exec_typed(&self.cmd) {
                         Ok(false) => {
                             tui.pending_quit = true;
                         }
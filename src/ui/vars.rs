@@ -0,0 +1,421 @@
+use crate::ed::{Address, Cmd, Command, Expansion, Offset, Pat, Point, Re, SysPoint};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// What a `${name}` is swapped for until a script is parsed, so a value can't change how a
+/// command parses
+const OPEN: char = '\u{E000}';
+const CLOSE: char = '\u{E001}';
+
+/// Variables a script may refer to as `${name}`
+///
+/// Definitions given on the command line are looked up first and then the environment.  `$${`
+/// is kept as a literal `${`.
+///
+/// In a script, variables are expanded after it is parsed: within regexes, patterns, file names,
+/// shell commands and other values commands take, but not within comments or the text given to
+/// `a`, `i` and `c`.
+#[derive(Debug, Default, Clone)]
+pub struct Vars {
+    defines: HashMap<String, String>,
+}
+
+/// A `name=value` definition
+#[derive(Debug, PartialEq)]
+pub struct Define(pub String, pub String);
+
+impl FromStr for Define {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Define, String> {
+        match s.split_once('=') {
+            Some((name, value)) if is_name(name) => Ok(Define(name.to_string(), value.to_string())),
+            _ => Err(format!("expected name=value but got {:?}", s)),
+        }
+    }
+}
+
+impl Vars {
+    /// Create variables from command line definitions, later ones winning
+    pub fn new(defines: Vec<Define>) -> Vars {
+        let defines = defines
+            .into_iter()
+            .map(|Define(name, value)| (name, value))
+            .collect();
+
+        Vars { defines }
+    }
+
+    /// Replace each `${name}` in some text, or give the name of the first undefined variable
+    pub fn expand(&self, text: &str) -> Result<String, String> {
+        self.fill(&mark(text))
+    }
+
+    /// Parse a script, expanding its variables
+    ///
+    /// Errors give the (0-based) line which failed along with why.
+    pub(crate) fn script(&self, content: &str) -> Result<Vec<Command>, (usize, String)> {
+        let marked = mark(content);
+        let cmds = Command::from_content_lines(&marked)
+            .map_err(|(line, pos)| (pos, format!("failed to parse command: {}", unmark(line))))?;
+
+        cmds.into_iter()
+            .map(|(pos, mut cmd)| {
+                self.command(&mut cmd).map_err(|err| (pos, err))?;
+                Ok(cmd)
+            })
+            .collect()
+    }
+
+    /// Parse an expression given on the command line, expanding its variables
+    pub(crate) fn expr(&self, expr: &str) -> Result<Vec<Command>, String> {
+        let marked = mark(expr);
+        let mut cmds = Command::from_expr(&marked).or(Err("is an invalid expression"))?;
+
+        for cmd in &mut cmds {
+            self.command(cmd)?;
+        }
+
+        Ok(cmds)
+    }
+
+    fn command(&self, cmd: &mut Command) -> Result<(), String> {
+        match cmd {
+            Command::Print(addr)
+            | Command::Delete(addr)
+            | Command::Join(addr)
+            | Command::Yank(addr)
+            | Command::Uniq(addr, _)
+            | Command::Tac(addr)
+            | Command::Indent(addr, _)
+            | Command::Dedent(addr, _)
+            | Command::Reflow(addr, _)
+            | Command::Translit(addr, _)
+            | Command::Increment(addr, _, _) => self.address(addr),
+
+            Command::Scroll(offset, _)
+            | Command::Nop(offset)
+            | Command::Mark(offset, _)
+            | Command::Paste(offset) => self.offset(offset),
+
+            Command::Append(offset, text) | Command::Insert(offset, text) => {
+                text.iter_mut()
+                    .flatten()
+                    .for_each(|line| *line = unmark(line));
+                self.offset(offset)
+            }
+
+            Command::Change(addr, text) => {
+                text.iter_mut()
+                    .flatten()
+                    .for_each(|line| *line = unmark(line));
+                self.address(addr)
+            }
+
+            Command::JoinWith(addr, sep) => {
+                *sep = self.value(sep)?;
+                self.address(addr)
+            }
+
+            Command::Move(addr, offset) | Command::Transfer(addr, offset) => {
+                self.address(addr)?;
+                self.offset(offset)
+            }
+
+            Command::Split(addr, re, _) | Command::Align(addr, re, _) => {
+                self.address(addr)?;
+                self.opt_re(re)
+            }
+
+            Command::Subst(addr, re, pat, _) => {
+                self.address(addr)?;
+                self.opt_re(re)?;
+
+                if let Some(Pat::Expansion(exps)) = pat {
+                    for exp in exps {
+                        if let Expansion::Lit(lit) = exp {
+                            *lit = self.value(lit)?;
+                        }
+                    }
+                }
+
+                Ok(())
+            }
+
+            Command::Global(addr, re, cmds) | Command::Void(addr, re, cmds) => {
+                self.address(addr)?;
+                self.opt_re(re)?;
+                cmds.iter_mut().try_for_each(|cmd| self.command(cmd))
+            }
+
+            Command::Sort(addr, flags) => {
+                self.address(addr)?;
+                self.opt_re(&mut flags.key)
+            }
+
+            Command::Write(addr, dest, _) => {
+                self.address(addr)?;
+                self.syspoint(dest)
+            }
+
+            Command::Read(offset, src) => {
+                self.offset(offset)?;
+                self.syspoint(src)
+            }
+
+            Command::Edit(point) | Command::Source(point) | Command::Journal(point) => {
+                self.syspoint(point)
+            }
+
+            Command::Run(cmd) => self.cmd(cmd),
+            Command::Branch(addr, _, _) => addr.as_mut().map_or(Ok(()), |addr| self.address(addr)),
+            Command::Set(_, value) => {
+                *value = self.value(value)?;
+                Ok(())
+            }
+
+            Command::NextBuffer
+            | Command::PrevBuffer
+            | Command::Quit
+            | Command::Label(_)
+            | Command::Settings(_) => Ok(()),
+        }
+    }
+
+    fn address(&self, addr: &mut Address) -> Result<(), String> {
+        match addr {
+            Address::Line(offset) => self.offset(offset),
+            Address::Range { start, end } => {
+                self.offset(start)?;
+                self.offset(end)
+            }
+        }
+    }
+
+    fn offset(&self, offset: &mut Offset) -> Result<(), String> {
+        match offset {
+            Offset::Nil(Point::Ref(re) | Point::Reb(re))
+            | Offset::Relf(Point::Ref(re) | Point::Reb(re), _)
+            | Offset::Relb(Point::Ref(re) | Point::Reb(re), _) => self.re(re),
+            _ => Ok(()),
+        }
+    }
+
+    fn opt_re(&self, re: &mut Option<Re>) -> Result<(), String> {
+        re.as_mut().map_or(Ok(()), |re| self.re(re))
+    }
+
+    fn re(&self, re: &mut Re) -> Result<(), String> {
+        if !re.source().contains(OPEN) {
+            return Ok(());
+        }
+
+        let source = self.value(re.source())?;
+        *re = Re::with_flags(&source, re.flags())
+            .map_err(|_| format!("makes the invalid regex {}", source))?;
+
+        Ok(())
+    }
+
+    fn syspoint(&self, point: &mut SysPoint) -> Result<(), String> {
+        match point {
+            SysPoint::Filename => Ok(()),
+            SysPoint::File(name) => {
+                *name = self.value(name)?;
+                Ok(())
+            }
+            SysPoint::Command(cmd) => self.cmd(cmd),
+        }
+    }
+
+    fn cmd(&self, cmd: &mut Cmd) -> Result<(), String> {
+        if let Cmd::System(line) = cmd {
+            *line = self.value(line)?;
+        }
+
+        Ok(())
+    }
+
+    /// Text with its marked variables filled in, or why it couldn't be
+    fn value(&self, text: &str) -> Result<String, String> {
+        self.fill(text)
+            .map_err(|name| format!("uses undefined variable {}", name))
+    }
+
+    /// Put the value of each marked variable in its place, or give the name of the first
+    /// undefined variable
+    fn fill(&self, text: &str) -> Result<String, String> {
+        let mut buf = String::with_capacity(text.len());
+
+        for (pos, part) in text.split(OPEN).enumerate() {
+            match part.split_once(CLOSE) {
+                Some((name, rest)) if pos > 0 => {
+                    buf.push_str(&self.lookup(name).ok_or_else(|| name.to_string())?);
+                    buf.push_str(rest);
+                }
+                _ => buf.push_str(part),
+            }
+        }
+
+        Ok(buf)
+    }
+
+    fn lookup(&self, name: &str) -> Option<String> {
+        self.defines
+            .get(name)
+            .cloned()
+            .or_else(|| std::env::var(name).ok())
+    }
+}
+
+/// Swap each `${name}` in some text for a marker
+fn mark(text: &str) -> String {
+    let mut buf = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(pos) = rest.find("${") {
+        if rest[..pos].ends_with('$') {
+            buf.push_str(&rest[..pos - 1]);
+            buf.push_str("${");
+            rest = &rest[pos + 2..];
+            continue;
+        }
+
+        let name_len = rest[pos + 2..].find('}');
+        let name = match name_len.map(|len| &rest[pos + 2..pos + 2 + len]) {
+            Some(name) if is_name(name) => name,
+            _ => {
+                buf.push_str(&rest[..pos + 2]);
+                rest = &rest[pos + 2..];
+                continue;
+            }
+        };
+
+        buf.push_str(&rest[..pos]);
+        buf.push(OPEN);
+        buf.push_str(name);
+        buf.push(CLOSE);
+        rest = &rest[pos + 3 + name.len()..];
+    }
+
+    buf.push_str(rest);
+
+    buf
+}
+
+/// Put back the `${name}` each marker stood for
+fn unmark(text: &str) -> String {
+    text.replace(OPEN, "${").replace(CLOSE, "}")
+}
+
+fn is_name(name: &str) -> bool {
+    let mut chars = name.chars();
+
+    matches!(chars.next(), Some(ch) if ch.is_ascii_alphabetic() || ch == '_')
+        && chars.all(|ch| ch.is_ascii_alphanumeric() || ch == '_')
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn vars() -> Vars {
+        Vars::new(vec![
+            "service=billing".parse().unwrap(),
+            "port=8080".parse().unwrap(),
+        ])
+    }
+
+    #[test]
+    fn test_define() {
+        assert_eq!(
+            Ok(Define("a".to_string(), "b=c".to_string())),
+            "a=b=c".parse()
+        );
+        assert!("=b".parse::<Define>().is_err());
+        assert!("nothing".parse::<Define>().is_err());
+    }
+
+    #[test]
+    fn test_expand() {
+        assert_eq!(
+            Ok("s/old-billing/new-billing:8080/".to_string()),
+            vars().expand("s/old-${service}/new-${service}:${port}/")
+        );
+    }
+
+    #[test]
+    fn test_literal() {
+        assert_eq!(
+            Ok("${service} $1 ${ x}".to_string()),
+            vars().expand("$${service} $1 ${ x}")
+        );
+    }
+
+    #[test]
+    fn test_env() {
+        std::env::set_var("ER_VARS_TEST", "from env");
+
+        assert_eq!(
+            Ok("w from env".to_string()),
+            vars().expand("w ${ER_VARS_TEST}")
+        );
+    }
+
+    #[test]
+    fn test_script() {
+        let script =
+            "# from ${nowhere}\n/${service}/s/x/${port}/\n$a\n${service} at ${nowhere}\n.\n";
+        let cmds = vars().script(script).unwrap();
+
+        assert_eq!(
+            cmds.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            vec!["/billing/s/x/8080/", "$a \"${service} at ${nowhere}\""]
+        );
+    }
+
+    #[test]
+    fn test_no_injection() {
+        let vars = Vars::new(vec![
+            "path=a/b".parse().unwrap(),
+            "lines=x\n,d".parse().unwrap(),
+        ]);
+
+        let cmds = vars.script("s/${path}/${lines}/\nw ${path}.txt\n").unwrap();
+
+        match &cmds[0] {
+            Command::Subst(_, Some(re), Some(Pat::Expansion(exps)), _) => {
+                assert_eq!(re.source(), "a/b");
+                assert_eq!(exps, &[Expansion::Lit("x\n,d".to_string())]);
+            }
+            cmd => panic!("not a substitution: {:?}", cmd),
+        }
+
+        assert_eq!(
+            cmds[1],
+            Command::Write(Address::FULL, SysPoint::File("a/b.txt".to_string()), false)
+        );
+    }
+
+    #[test]
+    fn test_script_errors() {
+        assert_eq!(
+            Err((1, "uses undefined variable ER_VARS_UNDEFINED".to_string())),
+            vars().script("1p\ns/${ER_VARS_UNDEFINED}/x/\n")
+        );
+
+        let vars = Vars::new(vec!["open=(".parse().unwrap()]);
+        assert_eq!(
+            Err((0, "makes the invalid regex a(".to_string())),
+            vars.script("/a${open}/d\n")
+        );
+    }
+
+    #[test]
+    fn test_missing() {
+        assert_eq!(
+            Err("ER_VARS_UNDEFINED".to_string()),
+            vars().expand("r ${ER_VARS_UNDEFINED}")
+        );
+    }
+}
@@ -0,0 +1,143 @@
+use eyre::WrapErr;
+use ignore::{overrides::OverrideBuilder, WalkBuilder};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// How much of a file is looked at to tell whether it is binary
+const SNIFF_LEN: u64 = 8192;
+
+/// Finds the files under a directory a script should run on
+///
+/// Hidden files, files ignored by `.gitignore` or `.ignore` files and files which look binary
+/// are passed over.  Include globs limit the files to those matching any of them, and exclude
+/// globs drop the files matching any of them.
+#[derive(Debug, Default, Clone)]
+pub struct Walk {
+    include: Vec<String>,
+    exclude: Vec<String>,
+}
+
+impl Walk {
+    /// Create a walk with globs choosing which files are kept
+    pub fn new(include: Vec<String>, exclude: Vec<String>) -> Walk {
+        Walk { include, exclude }
+    }
+
+    /// The files under a directory in a stable order
+    pub fn files(&self, dir: &str) -> eyre::Result<Vec<String>> {
+        // include globs are matched after walking, as given to the walk they would bring back
+        // hidden and ignored files
+        let mut include = OverrideBuilder::new(dir);
+        for glob in &self.include {
+            include
+                .add(glob)
+                .wrap_err_with(|| format!("invalid include glob {}", glob))?;
+        }
+        let include = include.build()?;
+
+        let mut globs = OverrideBuilder::new(dir);
+        for glob in &self.exclude {
+            globs
+                .add(&format!("!{}", glob))
+                .wrap_err_with(|| format!("invalid exclude glob {}", glob))?;
+        }
+
+        let walk = WalkBuilder::new(dir)
+            .overrides(globs.build()?)
+            .require_git(false)
+            .sort_by_file_name(|a, b| a.cmp(b))
+            .build();
+
+        let mut files = Vec::new();
+        for entry in walk {
+            let entry = entry.wrap_err_with(|| format!("failed to walk {}", dir))?;
+
+            if !entry.file_type().is_some_and(|ty| ty.is_file()) || is_binary(entry.path()) {
+                continue;
+            }
+
+            if !self.include.is_empty() && !include.matched(entry.path(), false).is_whitelist() {
+                continue;
+            }
+
+            files.push(entry.path().display().to_string());
+        }
+
+        Ok(files)
+    }
+}
+
+/// Whether a file has a nul byte near its start, which text files do not
+fn is_binary(path: &Path) -> bool {
+    let mut head = Vec::new();
+
+    match File::open(path).and_then(|f| f.take(SNIFF_LEN).read_to_end(&mut head)) {
+        Ok(_) => head.contains(&0),
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs::{create_dir, write};
+    use tempfile::TempDir;
+
+    fn tree() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        let path = |name: &str| dir.path().join(name);
+
+        create_dir(path("src")).unwrap();
+        create_dir(path("target")).unwrap();
+
+        write(path(".gitignore"), "target\nsrc/gen.rs\n").unwrap();
+        write(path(".hidden.rs"), "hidden\n").unwrap();
+        write(path("README.md"), "readme\n").unwrap();
+        write(path("src/lib.rs"), "lib\n").unwrap();
+        write(path("src/main.rs"), "main\n").unwrap();
+        write(path("src/gen.rs"), "generated\n").unwrap();
+        write(path("src/logo.png"), b"\x89PNG\x00\x01").unwrap();
+        write(path("target/out.rs"), "built\n").unwrap();
+
+        dir
+    }
+
+    fn names(dir: &TempDir, walk: Walk) -> Vec<String> {
+        let root = dir.path().display().to_string();
+
+        walk.files(&root)
+            .unwrap()
+            .into_iter()
+            .map(|file| file[root.len() + 1..].to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_ignored() {
+        let dir = tree();
+
+        assert_eq!(
+            vec!["README.md", "src/lib.rs", "src/main.rs"],
+            names(&dir, Walk::default())
+        );
+    }
+
+    #[test]
+    fn test_globs() {
+        let dir = tree();
+
+        assert_eq!(
+            vec!["src/lib.rs", "src/main.rs"],
+            names(&dir, Walk::new(vec!["*.rs".to_string()], Vec::new()))
+        );
+
+        assert_eq!(
+            vec!["src/lib.rs"],
+            names(
+                &dir,
+                Walk::new(vec!["*.rs".to_string()], vec!["main.rs".to_string()])
+            )
+        );
+    }
+}